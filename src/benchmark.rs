@@ -0,0 +1,132 @@
+// Highway benchmark: a chart packed with notes all inside the look-ahead of
+// the highway, drawn as fast as the screen takes them with neither media nor
+// UI, and the frame times printed once enough frames were drawn.
+use std::time::Instant;
+
+use glium::glutin;
+use glium::Surface;
+
+use mechanical::chart::{Chart, Note};
+use mechanical::gameplay::Gameplay;
+use mechanical::highway::{self, JUDGEMENT_LINE_MARGIN};
+use mechanical::profile::Profile;
+
+use crate::highway_renderer::HighwayRenderer;
+use crate::media_player::media_player::palette;
+use crate::support;
+
+const WIDTH: u32 = 800;
+const HEIGHT: u32 = 600;
+/// Lanes the notes are spread over.
+const KEYS: &str = "asdfjkl;";
+/// Frames drawn before measuring, while the driver settles.
+const WARMUP_FRAMES: usize = 30;
+
+pub fn main(notes: usize, frames: usize) {
+    let event_loop = glutin::event_loop::EventLoop::new();
+    let window = glutin::window::WindowBuilder::new()
+        .with_title("Mechanical Rhythmboard - Benchmark")
+        .with_inner_size(glutin::dpi::LogicalSize::new(WIDTH, HEIGHT))
+        .with_resizable(false);
+    let context = glutin::ContextBuilder::new().with_vsync(false).with_multisampling(4);
+    let display = glium::Display::new(window, context, &event_loop).unwrap();
+    let assets = find_folder::Search::KidsThenParents(1, 1).for_folder("assets").unwrap();
+    let font = conrod_core::text::font::from_file(assets.join("Menlo-Regular.ttf")).unwrap();
+    let mut highway_renderer = HighwayRenderer::new(&display, &font).unwrap();
+
+    let profile = Profile::new("benchmark");
+    let palette = palette(profile.theme);
+    let area = highway::Rect {
+        x: 0.0,
+        y: 0.0,
+        w: WIDTH as f64,
+        h: HEIGHT as f64,
+    };
+    let gameplay = Gameplay::new(Some(packed_chart(notes, &profile, area)));
+
+    let config = support::LoopConfig {
+        max_fps: None,
+        ..support::LoopConfig::default()
+    };
+    // Time of the whole frame, from one redraw to the next, then of its layout
+    // and of its drawing
+    let mut frame_times = Vec::with_capacity(frames);
+    let mut layout_times = Vec::with_capacity(frames);
+    let mut draw_times = Vec::with_capacity(frames);
+    let mut drawn = 0;
+    let mut last_frame: Option<Instant> = None;
+    support::run_loop(display, event_loop, config, move |request, display| match request {
+        support::Request::Event { event, should_exit, .. } => {
+            if let glutin::event::Event::WindowEvent {
+                event: glutin::event::WindowEvent::CloseRequested,
+                ..
+            } = event
+            {
+                *should_exit = true;
+            }
+            if frame_times.len() >= frames {
+                print_report(notes, &frame_times, &layout_times, &draw_times);
+                *should_exit = true;
+            }
+        }
+        support::Request::Tick { .. } => {}
+        support::Request::SetUi { animating, .. } => *animating = true,
+        support::Request::Redraw => {
+            let start = Instant::now();
+            // Nothing is played, so every note stays on the highway
+            let frame = highway::frame(&gameplay, &profile, area, 0.0);
+            let laid_out = Instant::now();
+            let mut target = display.draw();
+            target.clear_color(0.0, 0.0, 0.0, 1.0);
+            highway_renderer
+                .draw(display, &mut target, &frame, &palette, [WIDTH as f64, HEIGHT as f64])
+                .unwrap();
+            target.finish().unwrap();
+            let end = Instant::now();
+
+            drawn += 1;
+            if drawn > WARMUP_FRAMES && frame_times.len() < frames {
+                if let Some(last_frame) = last_frame {
+                    frame_times.push((start - last_frame).as_secs_f64());
+                    layout_times.push((laid_out - start).as_secs_f64());
+                    draw_times.push((end - laid_out).as_secs_f64());
+                }
+            }
+            last_frame = Some(start);
+        }
+    })
+}
+
+// `notes` notes between time 0 and the top of the highway, over every lane
+fn packed_chart(notes: usize, profile: &Profile, area: highway::Rect) -> Chart {
+    let look_ahead = (area.h - JUDGEMENT_LINE_MARGIN) / profile.scroll_speed;
+    let keys: Vec<char> = KEYS.chars().collect();
+    let mut chart = Chart::default();
+    for index in 0..notes {
+        let time = look_ahead * index as f64 / notes.max(1) as f64;
+        chart.add_note(Note::key(time, keys[index % keys.len()]));
+    }
+    chart
+}
+
+fn print_report(notes: usize, frame_times: &[f64], layout_times: &[f64], draw_times: &[f64]) {
+    let milliseconds = |seconds: f64| seconds * 1000.0;
+    let mean = |times: &[f64]| times.iter().sum::<f64>() / times.len().max(1) as f64;
+    let mut sorted = frame_times.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let percentile = |fraction: f64| sorted[((sorted.len() - 1) as f64 * fraction).round() as usize];
+    println!("{} notes visible, {} frames", notes, frame_times.len());
+    if sorted.is_empty() {
+        return;
+    }
+    println!(
+        "Frame time: mean {:.2} ms, median {:.2} ms, 99th percentile {:.2} ms, worst {:.2} ms ({:.0} fps)",
+        milliseconds(mean(frame_times)),
+        milliseconds(percentile(0.5)),
+        milliseconds(percentile(0.99)),
+        milliseconds(sorted[sorted.len() - 1]),
+        1.0 / mean(frame_times)
+    );
+    println!("Layout: mean {:.2} ms", milliseconds(mean(layout_times)));
+    println!("Drawing: mean {:.2} ms", milliseconds(mean(draw_times)));
+}
@@ -0,0 +1,322 @@
+// Calibration scene: a metronome from `audiotestsrc` to tap along to for the
+// audio offset, then a square flashing on the same beat, muted, for the
+// visual offset. Both are saved in the profile, which takes them off the key
+// presses and draws the notes ahead.
+use std::time::Instant;
+
+use conrod_core::{widget, Colorable, Labelable, Positionable, Sizeable, Widget};
+use glium::glutin;
+use glium::Surface;
+use gstreamer::prelude::*;
+
+use mechanical::calibration::{self, Calibration};
+use mechanical::clock::GameClock;
+use mechanical::input::InputQueue;
+use mechanical::replay::KeyAction;
+
+use crate::media_player::media_player::{palette, save_profiles, select_profile};
+use crate::pipeline_clock::PipelineClock;
+use crate::support;
+use crate::AppWindow;
+
+/// Taps asked for in each test.
+const TAPS: usize = 16;
+/// Seconds between two clicks when `audiotestsrc` lets us choose, it ticks
+/// every second otherwise.
+const BEAT_INTERVAL: f64 = 0.5;
+/// How long the square stays lit, in seconds.
+const FLASH: f64 = 0.08;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Step {
+    Audio,
+    Visual,
+    Done,
+}
+
+struct Scene {
+    step: Step,
+    audio: Calibration,
+    visual: Calibration,
+    // Keys down, so that held keys repeating are not taps
+    held: Vec<u32>,
+}
+
+impl Scene {
+    fn new(interval: f64) -> Scene {
+        Scene {
+            step: Step::Audio,
+            audio: Calibration::new(interval),
+            visual: Calibration::new(interval),
+            held: Vec::new(),
+        }
+    }
+
+    fn tap(&mut self, time: f64) {
+        let calibration = match self.step {
+            Step::Audio => &mut self.audio,
+            Step::Visual => &mut self.visual,
+            Step::Done => return,
+        };
+        calibration.tap(time);
+        if calibration.offsets().len() >= TAPS {
+            self.step = match self.step {
+                Step::Audio => Step::Visual,
+                _ => Step::Done,
+            };
+        }
+    }
+}
+
+pub fn main(mut application_state: AppWindow) {
+    const WIDTH: u32 = 640;
+    const HEIGHT: u32 = 480;
+    let event_loop = glutin::event_loop::EventLoop::new();
+    let window = glutin::window::WindowBuilder::new()
+        .with_title("Mechanical Rhythmboard - Calibration")
+        .with_inner_size(glutin::dpi::LogicalSize::new(WIDTH, HEIGHT));
+    let context = glutin::ContextBuilder::new().with_vsync(true).with_multisampling(4);
+    let display = glium::Display::new(window, context, &event_loop).unwrap();
+    let mut ui = conrod_core::UiBuilder::new([WIDTH as f64, HEIGHT as f64]).build();
+    let assets = find_folder::Search::KidsThenParents(1, 1).for_folder("assets").unwrap();
+    application_state.app_font_id = Some(ui.fonts.insert_from_file(assets.join("Menlo-Regular.ttf")).unwrap());
+    let mut renderer = conrod_glium::Renderer::new(&display).unwrap();
+    let image_map = conrod_core::image::Map::<glium::texture::Texture2d>::new();
+    let ids = Ids::new(ui.widget_id_generator());
+
+    application_state.profiles = crate::media_player::media_player::open_profiles();
+    let name = application_state.profile.name.clone();
+    select_profile(&mut application_state, &name);
+
+    let (pipeline, metronome, interval) = match start_metronome() {
+        Ok(started) => started,
+        Err(err) => {
+            println!("{:#}", err);
+            return;
+        }
+    };
+    let clock = PipelineClock::new(&pipeline);
+    let mut scene = Scene::new(interval);
+    // Taps are timed when they arrive, like the keys of a play; any key will do
+    let mut taps = InputQueue::new();
+
+    support::run_loop(display, event_loop, support::LoopConfig::default(), move |request, display| match request {
+        support::Request::Event {
+            event,
+            should_update_ui,
+            should_exit,
+        } => {
+            if application_state.quit {
+                let _ = pipeline.set_state(gstreamer::State::Null);
+                *should_exit = true;
+                return;
+            }
+            if let Some(event) = support::convert_event(&event, &display.gl_window().window()) {
+                ui.handle_event(event);
+                *should_update_ui = true;
+            }
+            if let glutin::event::Event::WindowEvent { event, .. } = event {
+                match event {
+                    glutin::event::WindowEvent::CloseRequested
+                    | glutin::event::WindowEvent::KeyboardInput {
+                        input:
+                            glutin::event::KeyboardInput {
+                                virtual_keycode: Some(glutin::event::VirtualKeyCode::Escape),
+                                ..
+                            },
+                        ..
+                    } => application_state.quit = true,
+                    glutin::event::WindowEvent::KeyboardInput {
+                        input: glutin::event::KeyboardInput { scancode, state, .. },
+                        ..
+                    } => match state {
+                        glutin::event::ElementState::Pressed if !scene.held.contains(scancode) => {
+                            scene.held.push(*scancode);
+                            taps.push(Instant::now(), ' ', KeyAction::Down);
+                        }
+                        glutin::event::ElementState::Released => scene.held.retain(|held| held != scancode),
+                        _ => {}
+                    },
+                    _ => {}
+                }
+            }
+        }
+        support::Request::Tick { .. } => {}
+        support::Request::SetUi { needs_redraw, animating } => {
+            let step = scene.step;
+            for (_, tap) in taps.drain_timed(&clock) {
+                scene.tap(tap.time);
+            }
+            // The visual test is tapped to the flashes alone
+            if step == Step::Audio && scene.step != Step::Audio {
+                metronome.set_property("volume", &0.0f64).ok();
+            }
+            let time = clock.time();
+            let restart = set_widgets(&mut ui.set_widgets(), &ids, &mut application_state, &scene, time);
+            if restart {
+                scene = Scene::new(interval);
+                metronome.set_property("volume", &0.8f64).ok();
+            }
+            *needs_redraw = ui.has_changed();
+            // The flashes have to be drawn on time, not only on events
+            *animating = scene.step == Step::Visual;
+        }
+        support::Request::Redraw => {
+            let primitives = ui.draw();
+            renderer.fill(display, primitives, &image_map);
+            let mut target = display.draw();
+            target.clear_color(0.0, 0.0, 0.0, 1.0);
+            renderer.draw(display, &mut target, &image_map).unwrap();
+            target.finish().unwrap();
+        }
+    })
+}
+
+// A pipeline playing clicks on every beat, with the metronome element and the
+// seconds between beats
+fn start_metronome() -> anyhow::Result<(gstreamer::Element, gstreamer::Element, f64)> {
+    gstreamer::init()?;
+    let pipeline = gstreamer::parse_launch(
+        "audiotestsrc name=metronome wave=ticks freq=1000 volume=0.8 ! audioconvert ! autoaudiosink",
+    )?;
+    let metronome = pipeline
+        .clone()
+        .dynamic_cast::<gstreamer::Bin>()
+        .map_err(|_| anyhow::anyhow!("the metronome is not a pipeline"))?
+        .get_by_name("metronome")
+        .ok_or_else(|| anyhow::anyhow!("no metronome in the pipeline"))?;
+    // Older versions of GStreamer tick once a second
+    let interval = if metronome.find_property("tick-interval").is_some() {
+        let nanoseconds = (BEAT_INTERVAL * 1_000_000_000.0) as u64;
+        metronome.set_property("tick-interval", &nanoseconds)?;
+        BEAT_INTERVAL
+    } else {
+        1.0
+    };
+    pipeline.set_state(gstreamer::State::Playing)?;
+    Ok((pipeline, metronome, interval))
+}
+
+// The instructions of the step, the flashing square of the visual test, and
+// once both are done the offsets found. Returns whether to start again.
+fn set_widgets(ui: &mut conrod_core::UiCell, ids: &Ids, application_state: &mut AppWindow, scene: &Scene, time: f64) -> bool {
+    let font_id = application_state.app_font_id.unwrap();
+    let palette = palette(application_state.profile.theme);
+    widget::Canvas::new().color(palette.background).set(ids.canvas, ui);
+
+    let (calibration, instructions) = match scene.step {
+        Step::Audio => (&scene.audio, "Tap any key on every click"),
+        Step::Visual => (&scene.visual, "Tap any key every time the square lights up"),
+        Step::Done => (&scene.visual, ""),
+    };
+    if scene.step != Step::Done {
+        widget::Text::new(&format!("{}\n\n{} / {}", instructions, calibration.offsets().len(), TAPS))
+            .font_id(font_id)
+            .font_size(18)
+            .color(palette.text)
+            .center_justify()
+            .mid_top_with_margin_on(ids.canvas, 60.0)
+            .set(ids.instructions, ui);
+    }
+    if scene.step == Step::Visual {
+        let beat = (time / calibration.interval).floor() * calibration.interval;
+        let lit = time >= 0.0 && time - beat < FLASH;
+        widget::Rectangle::fill([120.0, 120.0])
+            .color(if lit { palette.text } else { palette.panel })
+            .middle_of(ids.canvas)
+            .set(ids.flash, ui);
+    }
+    if scene.step != Step::Done {
+        return false;
+    }
+
+    let describe = |name: &str, calibration: &Calibration| match calibration.offset() {
+        Some(offset) => format!(
+            "{} offset: {:+.1} ms ({} of {} taps kept)",
+            name,
+            offset * 1000.0,
+            calibration.kept().len(),
+            calibration.offsets().len()
+        ),
+        None => format!(
+            "{} offset: taps too uneven, {} of the {} needed were steady",
+            name,
+            calibration.kept().len(),
+            calibration::MIN_TAPS
+        ),
+    };
+    let summary = format!(
+        "Profile {}\n\n{}\n{}",
+        application_state.profile.name,
+        describe("Audio", &scene.audio),
+        describe("Visual", &scene.visual)
+    );
+    widget::Text::new(&summary)
+        .font_id(font_id)
+        .font_size(16)
+        .color(palette.text)
+        .center_justify()
+        .mid_top_with_margin_on(ids.canvas, 60.0)
+        .set(ids.instructions, ui);
+
+    let offsets = (scene.audio.offset(), scene.visual.offset());
+    let mut restart = false;
+    for _click in widget::Button::new()
+        .color(palette.button)
+        .label("Save")
+        .label_color(if offsets.0.is_some() || offsets.1.is_some() { palette.text } else { palette.dim_text })
+        .label_font_id(font_id)
+        .w_h(120.0, 36.0)
+        .bottom_left_with_margins_on(ids.canvas, 30.0, 30.0)
+        .set(ids.save, ui)
+    {
+        // Only the offsets found replace the profile ones
+        let profile = &mut application_state.profile;
+        if let Some(audio) = offsets.0 {
+            profile.audio_offset = audio;
+        }
+        if let Some(visual) = offsets.1 {
+            profile.visual_offset = visual;
+        }
+        let profile = profile.clone();
+        if let Some(ref mut profiles) = application_state.profiles {
+            profiles.update(profile);
+        }
+        save_profiles(application_state);
+        application_state.quit = true;
+    }
+    for _click in widget::Button::new()
+        .color(palette.button)
+        .label("Again")
+        .label_color(palette.text)
+        .label_font_id(font_id)
+        .w_h(120.0, 36.0)
+        .right_from(ids.save, 20.0)
+        .set(ids.again, ui)
+    {
+        restart = true;
+    }
+    for _click in widget::Button::new()
+        .color(palette.button)
+        .label("Cancel")
+        .label_color(palette.text)
+        .label_font_id(font_id)
+        .w_h(120.0, 36.0)
+        .right_from(ids.again, 20.0)
+        .set(ids.cancel, ui)
+    {
+        application_state.quit = true;
+    }
+    restart
+}
+
+widget_ids! {
+    struct Ids {
+        canvas,
+        instructions,
+        flash,
+        save,
+        again,
+        cancel,
+    }
+}
@@ -0,0 +1,81 @@
+// Calibration: tapping along a steady beat tells how late the sound or the
+// picture comes out. Each tap is compared with the closest beat, taps far
+// from the others are dropped as mistakes, and the rest are averaged.
+
+/// Taps needed, once the outliers are dropped, to trust the offset.
+pub const MIN_TAPS: usize = 8;
+/// Taps further from the median than this many median absolute deviations
+/// are outliers.
+const OUTLIER_DEVIATIONS: f64 = 3.0;
+/// Smallest deviation used, in seconds, so that very steady taps do not make
+/// every slightly different one an outlier.
+const MIN_DEVIATION: f64 = 0.005;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calibration {
+    /// Seconds between two beats, the first one at time 0.
+    pub interval: f64,
+    // Seconds between each tap and its closest beat
+    offsets: Vec<f64>,
+}
+
+impl Calibration {
+    pub fn new(interval: f64) -> Calibration {
+        Calibration {
+            interval,
+            offsets: Vec::new(),
+        }
+    }
+
+    /// A tap at `time`, on the same clock as the beats.
+    pub fn tap(&mut self, time: f64) {
+        let beat = (time / self.interval).round() * self.interval;
+        self.offsets.push(time - beat);
+    }
+
+    /// Offset of every tap, negative when early.
+    pub fn offsets(&self) -> &[f64] {
+        &self.offsets
+    }
+
+    /// The taps that are not outliers.
+    pub fn kept(&self) -> Vec<f64> {
+        inliers(&self.offsets)
+    }
+
+    /// Mean offset of the taps kept, `None` with fewer than `MIN_TAPS` of
+    /// them.
+    pub fn offset(&self) -> Option<f64> {
+        let kept = self.kept();
+        if kept.len() < MIN_TAPS {
+            return None;
+        }
+        Some(kept.iter().sum::<f64>() / kept.len() as f64)
+    }
+}
+
+/// `offsets` without those too far from their median.
+pub fn inliers(offsets: &[f64]) -> Vec<f64> {
+    if offsets.is_empty() {
+        return Vec::new();
+    }
+    let middle = median(offsets);
+    let deviations: Vec<f64> = offsets.iter().map(|offset| (offset - middle).abs()).collect();
+    let limit = OUTLIER_DEVIATIONS * median(&deviations).max(MIN_DEVIATION);
+    offsets
+        .iter()
+        .cloned()
+        .filter(|offset| (offset - middle).abs() <= limit)
+        .collect()
+}
+
+fn median(values: &[f64]) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    let middle = sorted.len() / 2;
+    if sorted.len() % 2 == 1 {
+        sorted[middle]
+    } else {
+        (sorted[middle - 1] + sorted[middle]) / 2.0
+    }
+}
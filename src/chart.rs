@@ -0,0 +1,383 @@
+// A chart is the list of characters the player has to press, each one tied to
+// the time of the media playing (in seconds), plus some information about the
+// song it was made for.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// Extension of the chart files written by the importers.
+pub const CHART_EXTENSION: &str = "mrchart";
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Metadata {
+    pub title: String,
+    pub artist: String,
+    pub creator: String,
+    /// Name of the difficulty, charts for the same song are told apart by it.
+    pub difficulty: String,
+    /// Media file, relative to the chart file.
+    pub audio: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Chart {
+    pub metadata: Metadata,
+    /// Tempo changes sorted by time, only used to draw beat lines and to
+    /// export to other formats.
+    pub timing_points: Vec<TimingPoint>,
+    /// Notes sorted by their start time.
+    pub notes: Vec<Note>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimingPoint {
+    /// Media time in seconds at which the tempo starts.
+    pub time: f64,
+    pub bpm: f64,
+    /// Beats per measure.
+    pub meter: u32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Note {
+    /// Media time, in seconds, at which the note has to be hit.
+    pub time: f64,
+    pub kind: NoteKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NoteKind {
+    /// A single character pressed at `Note::time`.
+    Key(char),
+    /// A character held down from `Note::time` until `end`.
+    Hold { key: char, end: f64 },
+    /// A whole word or phrase typed character by character.
+    Phrase(Phrase),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Phrase {
+    pub text: String,
+    pub timing: PhraseTiming,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PhraseTiming {
+    /// One target time per character of the phrase, in seconds.
+    PerCharacter(Vec<f64>),
+    /// Every character has to be typed between the note time and `end`.
+    Window { end: f64 },
+}
+
+impl Chart {
+    pub fn new(metadata: Metadata) -> Chart {
+        Chart {
+            metadata,
+            timing_points: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// Insert a note keeping the chart sorted by time, returns its index.
+    pub fn add_note(&mut self, note: Note) -> usize {
+        let index = self.notes
+            .iter()
+            .position(|n| n.time > note.time)
+            .unwrap_or(self.notes.len());
+        self.notes.insert(index, note);
+        index
+    }
+
+    /// Index of the phrase note that should be typed at `time`, if any.
+    pub fn phrase_at(&self, time: f64) -> Option<usize> {
+        self.notes.iter().position(|note| match note.kind {
+            NoteKind::Phrase(_) => note.time <= time && time <= note.end_time(),
+            _ => false,
+        })
+    }
+}
+
+impl Note {
+    pub fn key(time: f64, character: char) -> Note {
+        Note {
+            time,
+            kind: NoteKind::Key(character),
+        }
+    }
+
+    pub fn hold(time: f64, key: char, end: f64) -> Note {
+        Note {
+            time,
+            kind: NoteKind::Hold { key, end },
+        }
+    }
+
+    pub fn phrase(time: f64, phrase: Phrase) -> Note {
+        Note {
+            time,
+            kind: NoteKind::Phrase(phrase),
+        }
+    }
+
+    /// Media time at which the note stops accepting input.
+    pub fn end_time(&self) -> f64 {
+        match self.kind {
+            NoteKind::Key(_) => self.time,
+            NoteKind::Hold { end, .. } => end,
+            NoteKind::Phrase(ref phrase) => match phrase.timing {
+                PhraseTiming::PerCharacter(ref times) => times.last().cloned().unwrap_or(self.time),
+                PhraseTiming::Window { end } => end,
+            },
+        }
+    }
+}
+
+impl Phrase {
+    /// A phrase with one target time per character. Extra or missing times
+    /// are not checked here, see `Phrase::target_time`.
+    pub fn per_character(text: &str, times: Vec<f64>) -> Phrase {
+        Phrase {
+            text: text.to_string(),
+            timing: PhraseTiming::PerCharacter(times),
+        }
+    }
+
+    pub fn window(text: &str, end: f64) -> Phrase {
+        Phrase {
+            text: text.to_string(),
+            timing: PhraseTiming::Window { end },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Target time of the character at `index`, when the phrase has per
+    /// character timing.
+    pub fn target_time(&self, index: usize) -> Option<f64> {
+        match self.timing {
+            PhraseTiming::PerCharacter(ref times) => times.get(index).cloned(),
+            PhraseTiming::Window { .. } => None,
+        }
+    }
+}
+
+// Native format Section
+// A text file with one section per part of the chart:
+//
+//     [Metadata]
+//     title=Song
+//     audio=song.ogg
+//     [Timing]
+//     0.000 120.000 4
+//     [Notes]
+//     1.000 key a
+//     1.500 hold space 2.000
+//     2.000 phrase 3.000 hello world
+//     4.000 typed 4.000,4.100,4.200 hey
+
+/// Where the chart for `audio` with the given difficulty is saved.
+pub fn chart_path(audio: &Path, difficulty: &str) -> PathBuf {
+    let stem = audio
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let name = if difficulty.is_empty() {
+        format!("{}.{}", stem, CHART_EXTENSION)
+    } else {
+        format!("{} [{}].{}", stem, difficulty, CHART_EXTENSION)
+    };
+    audio.with_file_name(name)
+}
+
+// Characters that would break the space separated note lines get a name
+pub(crate) fn key_to_native(key: char) -> String {
+    match key {
+        ' ' => "space".to_string(),
+        '\t' => "tab".to_string(),
+        key => key.to_string(),
+    }
+}
+
+pub(crate) fn key_from_native(field: &str) -> Option<char> {
+    match field {
+        "space" => Some(' '),
+        "tab" => Some('\t'),
+        field => {
+            let mut characters = field.chars();
+            match (characters.next(), characters.next()) {
+                (Some(key), None) => Some(key),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn parse_time(field: Option<&str>) -> Result<f64> {
+    let field = field.ok_or_else(|| anyhow!("missing time"))?;
+    let time: f64 = field.parse().with_context(|| format!("invalid time {}", field))?;
+    if !time.is_finite() {
+        bail!("invalid time {}", field);
+    }
+    Ok(time)
+}
+
+/// Parse one line of the `[Notes]` section.
+pub fn parse_note(line: &str) -> Result<Note> {
+    let mut fields = line.splitn(3, ' ');
+    let time = parse_time(fields.next())?;
+    let kind = fields.next().ok_or_else(|| anyhow!("missing note kind"))?;
+    let rest = fields.next().unwrap_or("");
+    match kind {
+        "key" => {
+            let key = key_from_native(rest.trim()).ok_or_else(|| anyhow!("invalid key {}", rest))?;
+            Ok(Note::key(time, key))
+        }
+        "hold" => {
+            let mut fields = rest.split_whitespace();
+            let field = fields.next().unwrap_or("");
+            let key = key_from_native(field).ok_or_else(|| anyhow!("invalid key {}", field))?;
+            let end = parse_time(fields.next())?;
+            Ok(Note::hold(time, key, end))
+        }
+        "phrase" => {
+            let mut fields = rest.splitn(2, ' ');
+            let end = parse_time(fields.next())?;
+            let text = fields.next().unwrap_or("");
+            Ok(Note::phrase(time, Phrase::window(text, end)))
+        }
+        "typed" => {
+            let mut fields = rest.splitn(2, ' ');
+            let times = fields
+                .next()
+                .unwrap_or("")
+                .split(',')
+                .map(|field| parse_time(Some(field)))
+                .collect::<Result<Vec<f64>>>()?;
+            let text = fields.next().unwrap_or("");
+            Ok(Note::phrase(time, Phrase::per_character(text, times)))
+        }
+        kind => bail!("unknown note kind {}", kind),
+    }
+}
+
+fn note_to_native(note: &Note) -> String {
+    match note.kind {
+        NoteKind::Key(key) => format!("{:.3} key {}", note.time, key_to_native(key)),
+        NoteKind::Hold { key, end } => {
+            format!("{:.3} hold {} {:.3}", note.time, key_to_native(key), end)
+        }
+        NoteKind::Phrase(ref phrase) => match phrase.timing {
+            PhraseTiming::Window { end } => {
+                format!("{:.3} phrase {:.3} {}", note.time, end, phrase.text)
+            }
+            PhraseTiming::PerCharacter(ref times) => {
+                let times: Vec<String> = times.iter().map(|time| format!("{:.3}", time)).collect();
+                format!("{:.3} typed {} {}", note.time, times.join(","), phrase.text)
+            }
+        },
+    }
+}
+
+impl Chart {
+    pub fn load(path: &Path) -> Result<Chart> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        Chart::parse(&contents).with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_native())
+            .with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Chart> {
+        let mut chart = Chart::default();
+        let mut section = String::new();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len() - 1].to_string();
+                continue;
+            }
+            chart
+                .parse_line(&section, line)
+                .with_context(|| format!("line {}", number + 1))?;
+        }
+        // Files edited by hand may not be sorted
+        chart.notes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        chart.timing_points.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+        Ok(chart)
+    }
+
+    /// Parse one line of `section` into the chart.
+    pub(crate) fn parse_line(&mut self, section: &str, line: &str) -> Result<()> {
+        match section {
+            "Metadata" => {
+                let mut fields = line.splitn(2, '=');
+                let key = fields.next().unwrap_or("").trim();
+                let value = fields.next().unwrap_or("").trim().to_string();
+                match key {
+                    "title" => self.metadata.title = value,
+                    "artist" => self.metadata.artist = value,
+                    "creator" => self.metadata.creator = value,
+                    "difficulty" => self.metadata.difficulty = value,
+                    "audio" => self.metadata.audio = value,
+                    key => bail!("unknown metadata {}", key),
+                }
+            }
+            "Timing" => {
+                let mut fields = line.split_whitespace();
+                let time = parse_time(fields.next())?;
+                let bpm = parse_time(fields.next())?;
+                let meter = match fields.next() {
+                    Some(field) => field.parse().with_context(|| format!("invalid meter {}", field))?,
+                    None => 4,
+                };
+                if bpm <= 0.0 {
+                    bail!("invalid bpm {}", bpm);
+                }
+                self.timing_points.push(TimingPoint { time, bpm, meter });
+            }
+            "Notes" => self.notes.push(parse_note(line)?),
+            section => bail!("line outside of a known section [{}]", section),
+        }
+        Ok(())
+    }
+
+    pub fn to_native(&self) -> String {
+        let mut lines = vec![
+            "[Metadata]".to_string(),
+            format!("title={}", self.metadata.title),
+            format!("artist={}", self.metadata.artist),
+            format!("creator={}", self.metadata.creator),
+            format!("difficulty={}", self.metadata.difficulty),
+            format!("audio={}", self.metadata.audio),
+            "[Timing]".to_string(),
+        ];
+        for point in &self.timing_points {
+            lines.push(format!("{:.3} {:.3} {}", point.time, point.bpm, point.meter));
+        }
+        lines.push("[Notes]".to_string());
+        lines.extend(self.notes.iter().map(note_to_native));
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    /// FNV-1a hash of the chart in the native format, to tell whether a
+    /// replay or a score was made on this chart.
+    pub fn hash(&self) -> u64 {
+        self.to_native()
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+    }
+}
@@ -0,0 +1,639 @@
+// Command line interface. Every subcommand takes its arguments after its
+// name and answers to --help; without a subcommand the demo media is played.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use mechanical::chart::{self, Chart};
+use mechanical::convert::{self, midi, osu, stepmania, ColumnKeys};
+use mechanical::exit::{self, FAILURE, SUCCESS, USAGE};
+use mechanical::ghost::Ghost;
+use mechanical::lint;
+use mechanical::practice;
+use mechanical::lyrics::Lyrics;
+use mechanical::profile::{self, Profile, Profiles};
+use mechanical::replay::{Playback, Replay};
+use mechanical::scores::ScoreDatabase;
+
+use crate::benchmark;
+use crate::calibrate;
+use crate::media_info;
+use crate::media_player::media_player;
+use crate::AppWindow;
+
+// Benchmark defaults
+const BENCHMARK_NOTES: usize = 5000;
+const BENCHMARK_FRAMES: usize = 600;
+
+const USAGE_TEXT: &str = "\
+Usage: mechanical [command] [arguments]
+
+Commands:
+  play <media> [--chart <file>] [--lyrics <file>] [--ghost <replay>]
+       [--profile <name>] [--fps <frames per second>] [--rate <rate>]
+      Play the media. The chart can be a native chart, .lrc or .srt lyrics.
+      The ghost is a replay of the chart to race against. The profile is
+      asked for when not given. The frame rate is 60 unless given, 0 draws
+      as fast as possible. The rate, from 0.5 to 0.9 in hundredths, plays
+      slower to practice, keeping the pitch; such plays are marked with
+      their rate.
+      F3 shows frame times and latencies.
+  edit <media> <chart>
+      Play the media and record every character typed into the chart.
+  convert [--from <format>] [--to <format>] <input> [--output <file>]
+          [--keys <characters>] [--track <n>] [--channel <n>]
+          [--pitches piano:<lowest>|<pitch=key,...>] [--per-word]
+      Convert a chart. From osu, sm, ssc, midi, lrc, srt or native (guessed
+      from the extension), to native (default) or osu.
+  info <media> [--json]
+      Show the streams of the media.
+  lint <chart> [--json] [--keys <characters>] [--media <file>]
+      Check a chart before publishing it.
+  replay <replay> <chart> [--media <file>]
+      Play a replay back with the media of the chart.
+  verify <replay> <chart>
+      Play a replay again without the media and check the score it claims.
+  scores <chart> [--profile <name>] [--top <n>]
+      Show the best plays of the chart, of every profile or of one.
+  profile [<name>] [--set <setting>=<value>]...
+      Without a name, list the profiles and how they play. With one, create
+      the profile if needed, change its settings and show them. Settings
+      are audio_offset and visual_offset in seconds, scroll_speed in pixels
+      per second, theme (dark or light), and bind, a key and the chart key
+      it plays (\"bind=j a\").
+  calibrate [--profile <name>]
+      Tap along to clicks, then to flashes, to find the audio and visual
+      offsets of the profile, the last one used unless given.
+  benchmark [--notes <n>] [--frames <n>]
+      Draw a highway of 5000 notes, or the number given, as fast as
+      possible and show the frame times over 600 frames or the number
+      given.
+  help
+      Show this message.
+
+Without a command, the demo media is played.
+
+Exit codes: 0 success, 1 the command failed or found errors, 2 wrong arguments
+or missing files.";
+
+pub fn run(args: &[String]) -> i32 {
+    let (command, args) = match args.split_first() {
+        Some((command, args)) => (command.as_str(), args),
+        None => return play_media(AppWindow::new(), media_player::DEMO_URI),
+    };
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE_TEXT);
+        return SUCCESS;
+    }
+    let result = match command {
+        "play" => play(args),
+        "edit" => edit(args),
+        "convert" => convert(args),
+        "info" => info(args),
+        "lint" => lint(args),
+        "replay" => replay(args),
+        "verify" => verify(args),
+        "scores" => scores(args),
+        "profile" => profile(args),
+        "calibrate" => calibrate(args),
+        "benchmark" => benchmark(args),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE_TEXT);
+            return SUCCESS;
+        }
+        command => return usage(&format!("unknown command {}", command)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("{:#}", err);
+            exit::code(&err)
+        }
+    }
+}
+
+// Arguments of a command: values after their --flag, and the rest in order
+struct Arguments {
+    flags: Vec<(String, Option<String>)>,
+    positional: Vec<String>,
+}
+
+impl Arguments {
+    // `valued` are the flags followed by a value
+    fn parse(args: &[String], valued: &[&str], switches: &[&str]) -> std::result::Result<Arguments, String> {
+        let mut arguments = Arguments { flags: Vec::new(), positional: Vec::new() };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            if valued.contains(&arg.as_str()) {
+                match args.next() {
+                    Some(value) => arguments.flags.push((arg.clone(), Some(value.clone()))),
+                    None => return Err(format!("{} needs a value", arg)),
+                }
+            } else if switches.contains(&arg.as_str()) {
+                arguments.flags.push((arg.clone(), None));
+            } else if arg.starts_with("--") {
+                return Err(format!("unknown option {}", arg));
+            } else {
+                arguments.positional.push(arg.clone());
+            }
+        }
+        Ok(arguments)
+    }
+
+    fn value(&self, flag: &str) -> Option<&str> {
+        self.flags
+            .iter()
+            .rev()
+            .find(|(name, _)| name == flag)
+            .and_then(|(_, value)| value.as_deref())
+    }
+
+    // Every value of a flag given more than once, in order
+    fn values(&self, flag: &str) -> Vec<&str> {
+        self.flags
+            .iter()
+            .filter(|(name, _)| name == flag)
+            .filter_map(|(_, value)| value.as_deref())
+            .collect()
+    }
+
+    fn switch(&self, flag: &str) -> bool {
+        self.flags.iter().any(|(name, _)| name == flag)
+    }
+}
+
+fn extension(path: &Path) -> String {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+fn play_media(application_state: AppWindow, media: &str) -> i32 {
+    match media_info::to_uri(media) {
+        Ok(uri) => media_player::main(application_state, &uri),
+        Err(err) => {
+            eprintln!("{:#}", err);
+            return FAILURE;
+        }
+    }
+    SUCCESS
+}
+
+/// `play <media> [--chart <file>] [--lyrics <file>] [--ghost <replay>] [--profile <name>] [--fps <n>] [--rate <r>]`
+fn play(args: &[String]) -> Result<i32> {
+    let valued = ["--chart", "--lyrics", "--ghost", "--profile", "--fps", "--rate"];
+    let arguments = match Arguments::parse(args, &valued, &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let media = match arguments.positional.as_slice() {
+        [media] => media,
+        _ => return Ok(usage("play needs one media")),
+    };
+    let mut application_state = AppWindow::new();
+    if let Some(chart_path) = arguments.value("--chart") {
+        let chart_path = Path::new(chart_path);
+        match extension(chart_path).as_str() {
+            "lrc" | "srt" => {
+                let lyrics = Lyrics::load(chart_path)?;
+                application_state.gameplay.chart = Some(lyrics.to_chart(true));
+                application_state.lyrics = lyrics;
+            }
+            _ => application_state.gameplay.chart = Some(Chart::load(chart_path)?),
+        }
+        application_state.use_subtitle_chart = false;
+    }
+    if let Some(lyrics_path) = arguments.value("--lyrics") {
+        application_state.lyrics = Lyrics::load(Path::new(lyrics_path))?;
+    }
+    if let Some(ghost_path) = arguments.value("--ghost") {
+        let chart = match application_state.gameplay.chart {
+            Some(ref chart) => chart,
+            None => return Ok(usage("--ghost needs a --chart")),
+        };
+        application_state.ghost = Some(Ghost::new(Replay::load(Path::new(ghost_path))?, chart)?);
+    }
+    if let Some(name) = arguments.value("--profile") {
+        if !profile::valid_name(name) {
+            return Ok(usage(&format!("invalid profile name {:?}", name)));
+        }
+        application_state.profile = Profile::new(name);
+        application_state.picking_profile = false;
+    }
+    match arguments.value("--fps").map(str::parse::<f64>) {
+        None => {}
+        Some(Ok(fps)) if fps == 0.0 => application_state.loop_config.max_fps = None,
+        Some(Ok(fps)) if fps > 0.0 && fps.is_finite() => application_state.loop_config.max_fps = Some(fps),
+        Some(_) => return Ok(usage("--fps needs a number of frames per second, or 0")),
+    }
+    match arguments.value("--rate").map(str::parse::<f64>) {
+        None => {}
+        Some(Ok(rate)) if practice::valid_rate(rate) => {
+            application_state.gameplay.set_rate(rate);
+            if rate != 1.0 {
+                application_state.pending_rate = Some(rate);
+            }
+        }
+        Some(_) => {
+            return Ok(usage(&format!(
+                "--rate needs a rate from {} to {} in hundredths, or 1",
+                practice::MIN_RATE,
+                practice::MAX_RATE
+            )))
+        }
+    }
+    Ok(play_media(application_state, media))
+}
+
+/// `edit <media> <chart>`
+fn edit(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &[], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let (media, chart_path) = match arguments.positional.as_slice() {
+        [media, chart_path] => (media, PathBuf::from(chart_path)),
+        _ => return Ok(usage("edit needs a media and a chart")),
+    };
+    let mut application_state = AppWindow::new();
+    // Keep editing an existing chart
+    let mut chart = if chart_path.exists() {
+        Chart::load(&chart_path)?
+    } else {
+        Chart::default()
+    };
+    if chart.metadata.audio.is_empty() {
+        chart.metadata.audio = Path::new(media)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+    }
+    application_state.gameplay.chart = Some(chart);
+    application_state.use_subtitle_chart = false;
+    application_state.editing = Some(chart_path);
+    application_state.picking_profile = false;
+    Ok(play_media(application_state, media))
+}
+
+// Charts of `input`, in the format given or guessed from the extension
+fn read_charts(input: &Path, from: &str, arguments: &Arguments) -> Result<(Vec<Chart>, convert::Report)> {
+    let keys = arguments.value("--keys").map(ColumnKeys::parse);
+    let contents = || fs::read_to_string(input).with_context(|| format!("Could not read {}", input.display()));
+    match from {
+        "osu" => osu::import(&contents()?, keys).map(|(chart, report)| (vec![chart], report)),
+        "sm" | "ssc" => stepmania::import(&contents()?, keys),
+        "mid" | "midi" => {
+            let mut options = midi::Options::default();
+            if let Some(track) = arguments.value("--track") {
+                options.track = Some(track.parse().map_err(|_| anyhow!("invalid track {}", track))?);
+            }
+            if let Some(channel) = arguments.value("--channel") {
+                options.channel = Some(channel.parse().map_err(|_| anyhow!("invalid channel {}", channel))?);
+            }
+            if let Some(pitches) = arguments.value("--pitches") {
+                options.pitches = match pitches.strip_prefix("piano:") {
+                    Some(lowest) => midi::PitchMap::Piano {
+                        lowest: lowest.parse().map_err(|_| anyhow!("invalid pitch {}", lowest))?,
+                    },
+                    None => midi::PitchMap::parse_table(pitches)?,
+                };
+            }
+            midi::import_file(input, &options).map(|(chart, report)| (vec![chart], report))
+        }
+        "lrc" | "srt" => {
+            let chart = Lyrics::load(input)?.to_chart(arguments.switch("--per-word"));
+            Ok((vec![chart], convert::Report::default()))
+        }
+        "native" | chart::CHART_EXTENSION => Ok((vec![Chart::load(input)?], convert::Report::default())),
+        from => bail!("unknown format {}", from),
+    }
+}
+
+/// `convert [--from <format>] [--to <format>] <input> [--output <file>] ...`
+fn convert(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(
+        args,
+        &["--from", "--to", "--output", "--keys", "--track", "--channel", "--pitches"],
+        &["--per-word"],
+    ) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let input = match arguments.positional.as_slice() {
+        [input] => Path::new(input),
+        _ => return Ok(usage("convert needs one input file")),
+    };
+    let guessed = extension(input);
+    let from = arguments.value("--from").unwrap_or(&guessed);
+    let to = arguments.value("--to").unwrap_or("native");
+    let output = arguments.value("--output").map(PathBuf::from);
+
+    // Beatmaps and simfiles are saved next to their audio by default
+    if to == "native" && output.is_none() {
+        let keys = arguments.value("--keys").map(ColumnKeys::parse);
+        let imported = match from {
+            "osu" => Some(osu::import_file(input, keys).map(|(path, report)| (vec![path], report))?),
+            "sm" | "ssc" => Some(stepmania::import_file(input, keys)?),
+            _ => None,
+        };
+        if let Some((paths, report)) = imported {
+            for path in paths {
+                println!("Saved {}", path.display());
+            }
+            print!("{}", report);
+            return Ok(SUCCESS);
+        }
+    }
+
+    let (charts, mut report) = read_charts(input, from, &arguments)?;
+    for chart in &charts {
+        // Several charts in one file are told apart by their difficulty
+        let base = output.clone().unwrap_or_else(|| input.to_path_buf());
+        let path = match (to, charts.len()) {
+            ("native", 1) if output.is_some() => base,
+            ("native", _) => chart::chart_path(&base, &chart.metadata.difficulty),
+            ("osu", 1) if output.is_some() => base,
+            ("osu", _) => chart::chart_path(&base, &chart.metadata.difficulty).with_extension("osu"),
+            (to, _) => return Ok(usage(&format!("can't convert to {}", to))),
+        };
+        if to == "osu" {
+            let keys = arguments.value("--keys").map(ColumnKeys::parse);
+            let export_report = osu::export_file(chart, &path, keys.as_ref())?;
+            for message in export_report.dropped {
+                report.drop(message);
+            }
+        } else {
+            chart.save(&path)?;
+        }
+        println!("Saved {}", path.display());
+    }
+    print!("{}", report);
+    Ok(SUCCESS)
+}
+
+/// `info <media> [--json]`
+fn info(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &[], &["--json"]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let media = match arguments.positional.as_slice() {
+        [media] => media,
+        _ => return Ok(usage("info needs one media")),
+    };
+    let info = media_info::info(media)?;
+    if arguments.switch("--json") {
+        println!("{}", info.to_json());
+    } else {
+        print!("{}", info);
+    }
+    Ok(SUCCESS)
+}
+
+/// `lint <chart> [--json] [--keys <characters>] [--media <file>]`
+fn lint(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &["--keys", "--media"], &["--json"]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let chart_path = match arguments.positional.as_slice() {
+        [chart_path] => chart_path,
+        _ => return Ok(usage("lint needs one chart file")),
+    };
+    let contents = fs::read_to_string(chart_path)
+        .with_context(|| format!("Could not read {}", chart_path))?;
+    let mut options = lint::Options::default();
+    options.layout = arguments.value("--keys").map(|keys| keys.chars().collect());
+
+    // The media is next to the chart unless given
+    let media = arguments.value("--media").map(String::from).or_else(|| {
+        contents
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("audio="))
+            .filter(|audio| !audio.trim().is_empty())
+            .map(|audio| {
+                Path::new(chart_path)
+                    .with_file_name(audio.trim())
+                    .to_string_lossy()
+                    .into_owned()
+            })
+    });
+    if let Some(ref media) = media {
+        match media_info::duration(media) {
+            Ok(duration) => options.media_duration = Some(duration),
+            Err(err) => eprintln!("Not checking the notes against the media: {:#}", err),
+        }
+    }
+
+    let diagnostics = lint::lint(&contents, &options);
+    if arguments.switch("--json") {
+        println!("{}", lint::to_json(&diagnostics));
+    } else {
+        for diagnostic in &diagnostics {
+            println!("{}:{}", chart_path, diagnostic);
+        }
+    }
+    if diagnostics.iter().any(|diagnostic| diagnostic.severity == lint::Severity::Error) {
+        Ok(FAILURE)
+    } else {
+        Ok(SUCCESS)
+    }
+}
+
+/// `replay <replay> <chart> [--media <file>]`
+fn replay(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &["--media"], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let (replay, chart_path) = match arguments.positional.as_slice() {
+        [replay, chart_path] => (Replay::load(Path::new(replay))?, Path::new(chart_path)),
+        _ => return Ok(usage("replay needs a replay and a chart")),
+    };
+    let chart = Chart::load(chart_path)?;
+    if chart.hash() != replay.chart_hash {
+        bail!("{} was not recorded on {}", replay.title, chart_path.display());
+    }
+    let media = match arguments.value("--media") {
+        Some(media) => media.to_string(),
+        None => chart_path.with_file_name(&chart.metadata.audio).to_string_lossy().into_owned(),
+    };
+    let mut application_state = AppWindow::new();
+    let playback = Playback::new(replay);
+    application_state.gameplay = playback.start(chart);
+    // Practice plays back at their rate, at first
+    let rate = application_state.gameplay.rate();
+    if rate != 1.0 {
+        application_state.pending_rate = Some(rate);
+    }
+    application_state.playback = Some(playback);
+    application_state.use_subtitle_chart = false;
+    application_state.picking_profile = false;
+    Ok(play_media(application_state, &media))
+}
+
+/// `verify <replay> <chart>`
+fn verify(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &[], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let (replay, chart) = match arguments.positional.as_slice() {
+        [replay, chart] => (Replay::load(Path::new(replay))?, Chart::load(Path::new(chart))?),
+        _ => return Ok(usage("verify needs a replay and a chart")),
+    };
+    let score = replay.verify(&chart)?;
+    println!(
+        "Verified: {} points, {:.2}% accuracy, {} max combo.",
+        score.points,
+        score.accuracy() * 100.0,
+        score.max_combo
+    );
+    Ok(SUCCESS)
+}
+
+/// `scores <chart> [--profile <name>] [--top <n>]`
+fn scores(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &["--profile", "--top"], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let chart = match arguments.positional.as_slice() {
+        [chart] => Chart::load(Path::new(chart))?,
+        _ => return Ok(usage("scores needs a chart")),
+    };
+    let count = match arguments.value("--top").map(str::parse::<usize>) {
+        None => 10,
+        Some(Ok(count)) => count,
+        Some(Err(_)) => return Ok(usage("--top needs a number")),
+    };
+    let database = ScoreDatabase::open(Path::new(media_player::SCORES_FILE))?;
+    if database.skipped > 0 {
+        eprintln!("Skipped {} unreadable plays in {}", database.skipped, media_player::SCORES_FILE);
+    }
+    let profile = arguments.value("--profile");
+    let top = database.top(chart.hash(), profile, count);
+    if top.is_empty() {
+        println!("No plays of {} yet.", chart.metadata.title);
+        return Ok(SUCCESS);
+    }
+    println!("{} [{}]", chart.metadata.title, chart.metadata.difficulty);
+    for (rank, entry) in top.iter().enumerate() {
+        let mods = if entry.mods.is_empty() { String::new() } else { format!(" +{}", entry.mods.join(",")) };
+        println!(
+            "{:>3}. {:<16} {:>8} points {:>7.2}% {:>5} max combo{}",
+            rank + 1,
+            entry.profile,
+            entry.score.points,
+            entry.accuracy * 100.0,
+            entry.score.max_combo,
+            mods
+        );
+    }
+    Ok(SUCCESS)
+}
+
+/// `profile [<name>] [--set <setting>=<value>]...`
+fn profile(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &["--set"], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    let path = Path::new(media_player::PROFILES_FILE);
+    let mut profiles = Profiles::load(path)?;
+    let name = match arguments.positional.as_slice() {
+        [] if arguments.values("--set").is_empty() => return list_profiles(&profiles),
+        [name] if profile::valid_name(name) => name,
+        [name] => return Ok(usage(&format!("invalid profile name {:?}", name))),
+        _ => return Ok(usage("profile takes at most one name")),
+    };
+    let mut profile = profiles.get(name).cloned().unwrap_or_else(|| Profile::new(name));
+    for setting in arguments.values("--set") {
+        let mut fields = setting.splitn(2, '=');
+        let key = fields.next().unwrap_or("");
+        let value = fields.next().unwrap_or("");
+        profile.set(key, value).with_context(|| format!("Could not set {}", setting))?;
+    }
+    println!("{}", profile.to_text());
+    profiles.update(profile);
+    profiles.save(path)?;
+    Ok(SUCCESS)
+}
+
+/// `calibrate [--profile <name>]`
+fn calibrate(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &["--profile"], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    if !arguments.positional.is_empty() {
+        return Ok(usage("calibrate takes no media"));
+    }
+    let mut application_state = AppWindow::new();
+    application_state.picking_profile = false;
+    if let Some(name) = arguments.value("--profile") {
+        if !profile::valid_name(name) {
+            return Ok(usage(&format!("invalid profile name {:?}", name)));
+        }
+        application_state.profile = Profile::new(name);
+    }
+    calibrate::main(application_state);
+    Ok(SUCCESS)
+}
+
+/// `benchmark [--notes <n>] [--frames <n>]`
+fn benchmark(args: &[String]) -> Result<i32> {
+    let arguments = match Arguments::parse(args, &["--notes", "--frames"], &[]) {
+        Ok(arguments) => arguments,
+        Err(message) => return Ok(usage(&message)),
+    };
+    if !arguments.positional.is_empty() {
+        return Ok(usage("benchmark takes no media"));
+    }
+    let notes = match arguments.value("--notes").map(str::parse::<usize>) {
+        None => BENCHMARK_NOTES,
+        Some(Ok(notes)) => notes,
+        Some(Err(_)) => return Ok(usage("--notes needs a number of notes")),
+    };
+    let frames = match arguments.value("--frames").map(str::parse::<usize>) {
+        None => BENCHMARK_FRAMES,
+        Some(Ok(frames)) if frames > 0 => frames,
+        Some(_) => return Ok(usage("--frames needs a number of frames")),
+    };
+    benchmark::main(notes, frames);
+    Ok(SUCCESS)
+}
+
+// Every profile with how much and how well it played
+fn list_profiles(profiles: &Profiles) -> Result<i32> {
+    if profiles.profiles.is_empty() {
+        println!("No profiles yet.");
+        return Ok(SUCCESS);
+    }
+    let database = ScoreDatabase::open(Path::new(media_player::SCORES_FILE))?;
+    for profile in &profiles.profiles {
+        let history = database.history(&profile.name);
+        let last = if profiles.last.as_deref() == Some(profile.name.as_str()) { " (last used)" } else { "" };
+        if history.is_empty() {
+            println!("{}{}: no plays", profile.name, last);
+            continue;
+        }
+        let mean = history.iter().map(|entry| entry.accuracy).sum::<f64>() / history.len() as f64;
+        let best = history.iter().map(|entry| entry.accuracy).fold(0.0, f64::max);
+        println!(
+            "{}{}: {} plays, {:.2}% mean accuracy, {:.2}% best",
+            profile.name,
+            last,
+            history.len(),
+            mean * 100.0,
+            best * 100.0
+        );
+    }
+    Ok(SUCCESS)
+}
+
+fn usage(message: &str) -> i32 {
+    eprintln!("{}\n\n{}", message, USAGE_TEXT);
+    USAGE
+}
@@ -0,0 +1,146 @@
+// Where gameplay gets the media time from. The player reads it from the
+// GStreamer pipeline, tests and headless runs move a manual clock by hand.
+use std::time::Instant;
+
+/// Media time source, in seconds.
+pub trait GameClock {
+    /// Current media time.
+    fn time(&self) -> f64;
+
+    /// Media seconds per real second.
+    fn rate(&self) -> f64 {
+        1.0
+    }
+
+    /// Media time at `instant`, a moment in the recent past, so that events
+    /// are timed when they happened rather than when they are handled.
+    fn time_at(&self, instant: Instant) -> f64 {
+        let elapsed = Instant::now().saturating_duration_since(instant).as_secs_f64();
+        self.time() - elapsed * self.rate()
+    }
+}
+
+/// Pipeline clock time between two position queries, in seconds.
+pub const QUERY_INTERVAL: f64 = 0.1;
+/// A queried position behind the interpolated time by less than this is
+/// jitter: the clock holds instead of going back.
+pub const MAX_JITTER: f64 = 0.05;
+
+/// Media time of a player whose position queries are costly and only as
+/// precise as the last buffer the sinks rendered: the position is queried
+/// now and then and moved forward with the pipeline clock in between.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Interpolation {
+    // Media time of the last query, and pipeline clock time it was made at
+    last_query: Option<(f64, f64)>,
+    // Last time returned, so the clock never runs backwards on jitter
+    last_time: f64,
+    // Media seconds per pipeline clock second
+    rate: f64,
+}
+
+impl Default for Interpolation {
+    fn default() -> Interpolation {
+        Interpolation {
+            last_query: None,
+            last_time: 0.0,
+            rate: 1.0,
+        }
+    }
+}
+
+impl Interpolation {
+    /// The media was sought to `time`, and plays at `rate` from there.
+    pub fn seek(&mut self, time: f64, rate: f64) {
+        self.rate = rate;
+        self.last_query = None;
+        self.last_time = time;
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Media time at `now` on the pipeline clock. `query` gives the media
+    /// position, it is only called while paused or when the last query is
+    /// too old.
+    pub fn time<F: FnOnce() -> f64>(&mut self, now: f64, playing: bool, query: F) -> f64 {
+        let interpolated = match self.last_query {
+            Some((position, at)) if playing && now - at < QUERY_INTERVAL => Some(position + (now - at) * self.rate),
+            _ => None,
+        };
+        let time = interpolated.unwrap_or_else(|| {
+            let position = query();
+            self.last_query = Some((position, now));
+            position
+        });
+        if playing && time < self.last_time && self.last_time - time < MAX_JITTER {
+            return self.last_time;
+        }
+        self.last_time = time;
+        time
+    }
+
+    /// Media seconds played in `seconds` of real time, none while paused.
+    pub fn media_seconds(&self, seconds: f64, playing: bool) -> f64 {
+        if playing {
+            seconds * self.rate
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Clock that only moves when told to, for tests and simulations.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ManualClock {
+    time: f64,
+}
+
+impl ManualClock {
+    pub fn new(time: f64) -> ManualClock {
+        ManualClock { time }
+    }
+
+    pub fn set(&mut self, time: f64) {
+        self.time = time;
+    }
+
+    pub fn advance(&mut self, seconds: f64) {
+        self.time += seconds;
+    }
+}
+
+impl GameClock for ManualClock {
+    fn time(&self) -> f64 {
+        self.time
+    }
+}
+
+/// Another clock set back by `offset` seconds of media time: the time the
+/// player hears when the sound comes out late. Keys and notes are both timed
+/// on it, so that notes are not expired before their keys can reach them.
+pub struct OffsetClock<'a, C: GameClock + ?Sized> {
+    clock: &'a C,
+    offset: f64,
+}
+
+impl<'a, C: GameClock + ?Sized> OffsetClock<'a, C> {
+    pub fn new(clock: &'a C, offset: f64) -> OffsetClock<'a, C> {
+        OffsetClock { clock, offset }
+    }
+}
+
+impl<'a, C: GameClock + ?Sized> GameClock for OffsetClock<'a, C> {
+    fn time(&self) -> f64 {
+        self.clock.time() - self.offset
+    }
+
+    fn rate(&self) -> f64 {
+        self.clock.rate()
+    }
+
+    fn time_at(&self, instant: Instant) -> f64 {
+        self.clock.time_at(instant) - self.offset
+    }
+}
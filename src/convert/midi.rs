@@ -0,0 +1,391 @@
+// Standard MIDI Files. The notes of one track or channel become keys (or
+// holds, when they last long enough) and the tempo map becomes timing points.
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::chart::{Chart, Note, TimingPoint};
+use crate::convert::Report;
+
+/// Microseconds per quarter note when the file has no tempo.
+const DEFAULT_TEMPO: u32 = 500_000;
+/// Piano-style layout: white keys on the home row, black keys above them.
+const PIANO_KEYS: &str = "awsedftgyhujkolp;'";
+
+/// How MIDI pitches become keys.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PitchMap {
+    /// Only the pitches in the table are kept.
+    Table(HashMap<u8, char>),
+    /// `PIANO_KEYS`, one key per semitone, starting with `lowest` on 'a'.
+    Piano { lowest: u8 },
+}
+
+impl PitchMap {
+    pub fn key(&self, pitch: u8) -> Option<char> {
+        match self {
+            PitchMap::Table(table) => table.get(&pitch).cloned(),
+            PitchMap::Piano { lowest } => pitch
+                .checked_sub(*lowest)
+                .and_then(|index| PIANO_KEYS.chars().nth(index as usize)),
+        }
+    }
+
+    /// "60=a,62=s" to a table.
+    pub fn parse_table(table: &str) -> Result<PitchMap> {
+        let mut keys = HashMap::new();
+        for entry in table.split(',').filter(|entry| !entry.trim().is_empty()) {
+            let mut fields = entry.splitn(2, '=');
+            let pitch: u8 = fields
+                .next()
+                .unwrap_or("")
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("invalid pitch in {}", entry))?;
+            let mut key = fields.next().unwrap_or("").chars();
+            match (key.next(), key.next()) {
+                (Some(key), None) => keys.insert(pitch, key),
+                _ => bail!("invalid key in {}", entry),
+            };
+        }
+        Ok(PitchMap::Table(keys))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Only read this track (0 based), every track otherwise.
+    pub track: Option<usize>,
+    /// Only read this channel (0 based), every channel otherwise.
+    pub channel: Option<u8>,
+    pub pitches: PitchMap,
+    /// Notes at least this long, in seconds, become holds.
+    pub min_hold: f64,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            track: None,
+            channel: None,
+            // Middle C on 'a'
+            pitches: PitchMap::Piano { lowest: 60 },
+            min_hold: 0.3,
+        }
+    }
+}
+
+// File reading Section
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn u8(&mut self) -> Result<u8> {
+        let byte = *self.bytes
+            .get(self.position)
+            .ok_or_else(|| anyhow!("unexpected end of file at byte {}", self.position))?;
+        self.position += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position + length;
+        if end > self.bytes.len() {
+            bail!("unexpected end of file at byte {}", self.bytes.len());
+        }
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(bytes.iter().fold(0, |value, byte| value << 8 | u32::from(*byte)))
+    }
+
+    // Variable length quantity, 7 bits per byte
+    fn varlen(&mut self) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = self.u8()?;
+            value = value << 7 | u32::from(byte & 0x7f);
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("variable length value too long at byte {}", self.position)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum EventKind {
+    NoteOn { channel: u8, pitch: u8 },
+    NoteOff { channel: u8, pitch: u8 },
+    /// Microseconds per quarter note.
+    Tempo(u32),
+    TimeSignature(u8),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Event {
+    tick: u64,
+    track: usize,
+    kind: EventKind,
+}
+
+fn read_track(bytes: &[u8], track: usize, events: &mut Vec<Event>, report: &mut Report) -> Result<()> {
+    let mut reader = Reader { bytes, position: 0 };
+    let mut tick = 0u64;
+    let mut running_status = None;
+    while reader.position < bytes.len() {
+        tick += u64::from(reader.varlen()?);
+        let mut status = reader.u8()?;
+        if status < 0x80 {
+            // Running status, the byte read is already the first data byte
+            status = running_status.ok_or_else(|| anyhow!("data byte without status"))?;
+            reader.position -= 1;
+        }
+        match status {
+            0xff => {
+                let kind = reader.u8()?;
+                let length = reader.varlen()? as usize;
+                let data = reader.take(length)?;
+                match kind {
+                    0x51 if length == 3 => events.push(Event {
+                        tick,
+                        track,
+                        kind: EventKind::Tempo(data.iter().fold(0, |value, byte| value << 8 | u32::from(*byte))),
+                    }),
+                    0x58 if length >= 1 => events.push(Event {
+                        tick,
+                        track,
+                        kind: EventKind::TimeSignature(data[0]),
+                    }),
+                    0x2f => break,
+                    _ => {}
+                }
+            }
+            0xf0 | 0xf7 => {
+                let length = reader.varlen()? as usize;
+                reader.take(length)?;
+                report.drop("system exclusive messages".to_string());
+            }
+            _ => {
+                running_status = Some(status);
+                let channel = status & 0x0f;
+                match status & 0xf0 {
+                    0x80 => {
+                        let pitch = reader.u8()?;
+                        reader.u8()?;
+                        events.push(Event { tick, track, kind: EventKind::NoteOff { channel, pitch } });
+                    }
+                    0x90 => {
+                        let pitch = reader.u8()?;
+                        let velocity = reader.u8()?;
+                        let kind = if velocity == 0 {
+                            EventKind::NoteOff { channel, pitch }
+                        } else {
+                            EventKind::NoteOn { channel, pitch }
+                        };
+                        events.push(Event { tick, track, kind });
+                    }
+                    0xa0 | 0xb0 | 0xe0 => {
+                        reader.take(2)?;
+                    }
+                    0xc0 | 0xd0 => {
+                        reader.u8()?;
+                    }
+                    _ => bail!("unknown status byte {:#x}", status),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Ticks to seconds through the tempo map
+struct TempoMap {
+    // Tick, seconds at that tick, microseconds per quarter from there on
+    changes: Vec<(u64, f64, u32)>,
+    ticks_per_quarter: f64,
+    // SMPTE divisions count ticks per second directly
+    ticks_per_second: Option<f64>,
+}
+
+impl TempoMap {
+    fn new(division: u16, events: &[Event]) -> Result<TempoMap> {
+        let ticks_per_second = if division & 0x8000 != 0 {
+            // Frames per second, negated, then ticks per frame
+            let frames = -i32::from((division >> 8) as u8 as i8);
+            let ticks_per_frame = division & 0xff;
+            if ![24, 25, 29, 30].contains(&frames) {
+                bail!("invalid SMPTE frame rate {}", frames);
+            }
+            if ticks_per_frame == 0 {
+                bail!("SMPTE division without ticks per frame");
+            }
+            Some(f64::from(frames) * f64::from(ticks_per_frame))
+        } else {
+            None
+        };
+        let mut map = TempoMap {
+            changes: vec![(0, 0.0, DEFAULT_TEMPO)],
+            ticks_per_quarter: f64::from(division.max(1)),
+            ticks_per_second,
+        };
+        for event in events {
+            if let EventKind::Tempo(tempo) = event.kind {
+                // A tempo of 0 would stop the music
+                let tempo = tempo.max(1);
+                let seconds = map.seconds(event.tick);
+                if event.tick == 0 {
+                    map.changes[0].2 = tempo;
+                } else {
+                    map.changes.push((event.tick, seconds, tempo));
+                }
+            }
+        }
+        Ok(map)
+    }
+
+    fn seconds(&self, tick: u64) -> f64 {
+        if let Some(ticks_per_second) = self.ticks_per_second {
+            return tick as f64 / ticks_per_second;
+        }
+        let (start_tick, start_seconds, tempo) = self.changes
+            .iter()
+            .rev()
+            .find(|(change_tick, _, _)| *change_tick <= tick)
+            .cloned()
+            .unwrap_or((0, 0.0, DEFAULT_TEMPO));
+        let quarters = (tick - start_tick) as f64 / self.ticks_per_quarter;
+        start_seconds + quarters * f64::from(tempo) / 1_000_000.0
+    }
+}
+
+/// Convert a Standard MIDI File.
+pub fn import(bytes: &[u8], options: &Options) -> Result<(Chart, Report)> {
+    let mut reader = Reader { bytes, position: 0 };
+    let mut report = Report::default();
+    if reader.take(4)? != b"MThd" {
+        bail!("not a Standard MIDI File");
+    }
+    let header_length = reader.u32()? as usize;
+    let header = reader.take(header_length)?;
+    if header.len() < 6 {
+        bail!("MIDI header too short");
+    }
+    let tracks = u16::from(header[2]) << 8 | u16::from(header[3]);
+    let division = u16::from(header[4]) << 8 | u16::from(header[5]);
+    if let Some(track) = options.track {
+        if track >= tracks as usize {
+            bail!("track {} selected, the file has {} tracks", track, tracks);
+        }
+    }
+
+    let mut events = Vec::new();
+    let mut track = 0;
+    while reader.position < bytes.len() && track < tracks as usize {
+        let kind = reader.take(4)?;
+        let length = reader.u32()? as usize;
+        let data = reader.take(length)?;
+        // Unknown chunks have to be skipped
+        if kind != b"MTrk" {
+            continue;
+        }
+        read_track(data, track, &mut events, &mut report)
+            .with_context(|| format!("track {}", track))?;
+        track += 1;
+    }
+    // Stable sort keeps the order of events on the same tick
+    events.sort_by_key(|event| event.tick);
+    let tempo_map = TempoMap::new(division, &events)?;
+
+    let mut chart = Chart::default();
+    let mut bpm = 60_000_000.0 / f64::from(tempo_map.changes[0].2.max(1));
+    let mut meter = 4;
+    chart.timing_points.push(TimingPoint { time: 0.0, bpm, meter });
+    for event in &events {
+        match event.kind {
+            EventKind::Tempo(tempo) => bpm = 60_000_000.0 / f64::from(tempo.max(1)),
+            EventKind::TimeSignature(numerator) => meter = u32::from(numerator.max(1)),
+            _ => continue,
+        }
+        // Tempo and time signature changes on the same tick share a point
+        let point = TimingPoint { time: tempo_map.seconds(event.tick), bpm, meter };
+        match chart.timing_points.last_mut() {
+            Some(last) if last.time == point.time => *last = point,
+            _ => chart.timing_points.push(point),
+        }
+    }
+
+    // Start tick of the notes being played, per channel and pitch
+    let mut playing: HashMap<(u8, u8), u64> = HashMap::new();
+    let mut unmapped = 0;
+    let mut unfinished = 0;
+    for event in &events {
+        if options.track.map(|track| track != event.track).unwrap_or(false) {
+            continue;
+        }
+        match event.kind {
+            EventKind::NoteOn { channel, .. } | EventKind::NoteOff { channel, .. }
+                if options.channel.map(|selected| selected != channel).unwrap_or(false) => {}
+            EventKind::NoteOn { channel, pitch } => {
+                // Restriking a playing note ends it first
+                if let Some(start) = playing.insert((channel, pitch), event.tick) {
+                    add_note(&mut chart, options, &tempo_map, pitch, start, event.tick, &mut unmapped);
+                }
+            }
+            EventKind::NoteOff { channel, pitch } => {
+                if let Some(start) = playing.remove(&(channel, pitch)) {
+                    add_note(&mut chart, options, &tempo_map, pitch, start, event.tick, &mut unmapped);
+                }
+            }
+            _ => {}
+        }
+    }
+    // In order, as the map has none
+    let mut playing: Vec<((u8, u8), u64)> = playing.into_iter().collect();
+    playing.sort_by_key(|((channel, pitch), start)| (*start, *channel, *pitch));
+    for ((_, pitch), start) in playing {
+        unfinished += 1;
+        add_note(&mut chart, options, &tempo_map, pitch, start, start, &mut unmapped);
+    }
+    report.drop_count(unmapped, "notes with a pitch outside of the key map");
+    report.drop_count(unfinished, "notes without note off (kept as taps)");
+    Ok((chart, report))
+}
+
+fn add_note(
+    chart: &mut Chart,
+    options: &Options,
+    tempo_map: &TempoMap,
+    pitch: u8,
+    start: u64,
+    end: u64,
+    unmapped: &mut usize,
+) {
+    let key = match options.pitches.key(pitch) {
+        Some(key) => key,
+        None => {
+            *unmapped += 1;
+            return;
+        }
+    };
+    let time = tempo_map.seconds(start);
+    let end = tempo_map.seconds(end);
+    if end - time >= options.min_hold {
+        chart.add_note(Note::hold(time, key, end));
+    } else {
+        chart.add_note(Note::key(time, key));
+    }
+}
+
+pub fn import_file(path: &Path, options: &Options) -> Result<(Chart, Report)> {
+    let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+    import(&bytes, options).with_context(|| format!("Could not convert {}", path.display()))
+}
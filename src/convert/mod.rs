@@ -0,0 +1,88 @@
+// Converting charts from and to the formats of other rhythm games.
+use std::fmt;
+
+pub mod midi;
+pub mod osu;
+pub mod stepmania;
+
+/// Keys assigned to the columns of a chart, from left to right.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ColumnKeys(pub Vec<char>);
+
+impl ColumnKeys {
+    /// Home row layouts for the usual column counts.
+    pub fn default_for(columns: usize) -> Option<ColumnKeys> {
+        let keys = match columns {
+            1 => " ",
+            2 => "fj",
+            3 => "f j",
+            4 => "dfjk",
+            5 => "df jk",
+            6 => "sdfjkl",
+            7 => "sdf jkl",
+            8 => "asdfjkl;",
+            9 => "asdf jkl;",
+            10 => "asdfvnjkl;",
+            _ => return None,
+        };
+        Some(ColumnKeys(keys.chars().collect()))
+    }
+
+    pub fn parse(keys: &str) -> ColumnKeys {
+        ColumnKeys(keys.chars().collect())
+    }
+
+    pub fn get(&self, column: usize) -> Option<char> {
+        self.0.get(column).cloned()
+    }
+
+    pub fn column_of(&self, key: char) -> Option<usize> {
+        self.0.iter().position(|k| *k == key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// What could not be converted, to be shown to whoever runs the conversion.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Report {
+    pub dropped: Vec<String>,
+}
+
+impl Report {
+    pub fn drop(&mut self, message: String) {
+        if !self.dropped.contains(&message) {
+            self.dropped.push(message);
+        }
+    }
+
+    /// Report `count` things of a kind, if there are any.
+    pub fn drop_count(&mut self, count: usize, what: &str) {
+        if count > 0 {
+            self.drop(format!("{} {}", count, what));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dropped.is_empty()
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.dropped.is_empty() {
+            return writeln!(f, "Everything was converted.");
+        }
+        writeln!(f, "Not converted:")?;
+        for message in &self.dropped {
+            writeln!(f, "  - {}", message)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,359 @@
+// osu!mania beatmaps (.osu files). Only the sections needed to play the
+// chart are read: [General], [Metadata], [Difficulty], [TimingPoints] and
+// [HitObjects], and only those are written when exporting.
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::chart::{self, Chart, Note, NoteKind, PhraseTiming, TimingPoint};
+use crate::convert::{ColumnKeys, Report};
+
+/// osu! playfield width, mania columns split it evenly.
+const PLAYFIELD_WIDTH: f64 = 512.0;
+const MANIA_MODE: &str = "3";
+// Hit object type bits
+const HIT_CIRCLE: u32 = 1;
+const HOLD_NOTE: u32 = 128;
+/// osu!mania plays up to 10 columns outside of co-op.
+const MAX_COLUMNS: usize = 10;
+/// Lane order used when no keys are given: keyboard rows, left to right.
+const KEYBOARD_ORDER: &str = "1234567890qwertyuiopasdfghjkl;zxcvbnm,./";
+
+// Key/value pairs of [General], [Metadata] and [Difficulty], plus the raw
+// lines of the other sections
+#[derive(Default)]
+struct Sections {
+    values: HashMap<String, String>,
+    lines: HashMap<String, Vec<(usize, String)>>,
+}
+
+fn read_sections(contents: &str) -> Sections {
+    let mut sections = Sections::default();
+    let mut section = String::new();
+    for (number, line) in contents.lines().enumerate() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+        match section.as_str() {
+            "General" | "Metadata" | "Difficulty" => {
+                let mut fields = line.splitn(2, ':');
+                let key = fields.next().unwrap_or("").trim().to_string();
+                let value = fields.next().unwrap_or("").trim().to_string();
+                sections.values.insert(key, value);
+            }
+            _ => sections
+                .lines
+                .entry(section.clone())
+                .or_insert_with(Vec::new)
+                .push((number + 1, line.to_string())),
+        }
+    }
+    sections
+}
+
+fn field<T: std::str::FromStr>(fields: &[&str], index: usize, line: usize) -> Result<T> {
+    let value = fields
+        .get(index)
+        .ok_or_else(|| anyhow!("line {}: missing field {}", line, index + 1))?;
+    value
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("line {}: invalid value {}", line, value))
+}
+
+/// Convert an osu!mania beatmap. Without `keys` the default layout for the
+/// beatmap key count is used.
+pub fn import(contents: &str, keys: Option<ColumnKeys>) -> Result<(Chart, Report)> {
+    let sections = read_sections(contents);
+    let value = |key: &str| sections.values.get(key).cloned().unwrap_or_default();
+    let mut report = Report::default();
+
+    if value("Mode") != MANIA_MODE {
+        bail!("not an osu!mania beatmap (Mode: {})", value("Mode"));
+    }
+    let columns: usize = value("CircleSize")
+        .parse::<f64>()
+        .ok()
+        .filter(|columns| *columns >= 1.0 && *columns <= MAX_COLUMNS as f64)
+        .map(|columns| columns as usize)
+        .ok_or_else(|| anyhow!("invalid key count {}", value("CircleSize")))?;
+    let keys = match keys {
+        Some(keys) => keys,
+        None => ColumnKeys::default_for(columns)
+            .ok_or_else(|| anyhow!("no default keys for {} columns", columns))?,
+    };
+    if keys.len() < columns {
+        bail!("{} keys given for {} columns", keys.len(), columns);
+    }
+
+    let mut chart = Chart::default();
+    chart.metadata.title = value("Title");
+    chart.metadata.artist = value("Artist");
+    chart.metadata.creator = value("Creator");
+    chart.metadata.difficulty = value("Version");
+    chart.metadata.audio = value("AudioFilename");
+    if chart.metadata.audio.is_empty() {
+        bail!("no AudioFilename");
+    }
+
+    let empty = Vec::new();
+    let mut scroll_changes = 0;
+    for (number, line) in sections.lines.get("TimingPoints").unwrap_or(&empty) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let time: f64 = field(&fields, 0, *number)?;
+        let beat_length: f64 = field(&fields, 1, *number)?;
+        let meter: u32 = field(&fields, 2, *number).unwrap_or(4);
+        if !time.is_finite() || !beat_length.is_finite() {
+            bail!("line {}: invalid timing point", number);
+        }
+        // Inherited points (negative beat length) only change scroll speed
+        let uninherited = fields.get(6).map(|f| f.trim() != "0").unwrap_or(true);
+        if !uninherited || beat_length <= 0.0 {
+            scroll_changes += 1;
+            continue;
+        }
+        chart.timing_points.push(TimingPoint {
+            time: time / 1000.0,
+            bpm: 60_000.0 / beat_length,
+            meter,
+        });
+    }
+    report.drop_count(scroll_changes, "scroll speed changes");
+
+    let mut hit_sounds = 0;
+    for (number, line) in sections.lines.get("HitObjects").unwrap_or(&empty) {
+        let fields: Vec<&str> = line.split(',').collect();
+        let x: f64 = field(&fields, 0, *number)?;
+        let time: f64 = field(&fields, 2, *number)?;
+        let kind: u32 = field(&fields, 3, *number)?;
+        let hit_sound: u32 = field(&fields, 4, *number).unwrap_or(0);
+        if !time.is_finite() {
+            bail!("line {}: invalid time {}", number, time);
+        }
+        let column = ((x * columns as f64 / PLAYFIELD_WIDTH).floor().max(0.0) as usize).min(columns - 1);
+        let key = keys.get(column).unwrap();
+        if hit_sound != 0 {
+            hit_sounds += 1;
+        }
+
+        if kind & HOLD_NOTE != 0 {
+            // The end time is the first value of the extras "end:sample..."
+            let end: f64 = fields
+                .get(5)
+                .and_then(|extras| extras.split(':').next())
+                .and_then(|end| end.trim().parse().ok())
+                .ok_or_else(|| anyhow!("line {}: hold note without end time", number))?;
+            if !end.is_finite() || end <= time {
+                bail!("line {}: invalid end time {}", number, end);
+            }
+            chart.add_note(Note::hold(time / 1000.0, key, end / 1000.0));
+        } else if kind & HIT_CIRCLE != 0 {
+            chart.add_note(Note::key(time / 1000.0, key));
+        } else {
+            report.drop(format!("line {}: hit object type {} is not a mania note", number, kind));
+        }
+    }
+    report.drop_count(hit_sounds, "notes with hit sounds (the sounds were dropped)");
+
+    if sections.lines.get("Events").map(|events| !events.is_empty()).unwrap_or(false) {
+        report.drop("background, video and storyboard events".to_string());
+    }
+    if !value("PreviewTime").is_empty() && value("PreviewTime") != "-1" {
+        report.drop("song select preview time".to_string());
+    }
+
+    Ok((chart, report))
+}
+
+/// Convert the beatmap at `path` and save the chart next to its audio file.
+/// Returns where the chart was saved.
+pub fn import_file(path: &Path, keys: Option<ColumnKeys>) -> Result<(PathBuf, Report)> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let (chart, report) = import(&contents, keys)
+        .with_context(|| format!("Could not convert {}", path.display()))?;
+    let audio = path.with_file_name(&chart.metadata.audio);
+    let chart_path = chart::chart_path(&audio, &chart.metadata.difficulty);
+    chart.save(&chart_path)?;
+    Ok((chart_path, report))
+}
+
+// Export Section
+// A key press of the exported chart, phrases with per character timing are
+// split into one press per character
+struct Press {
+    time: f64,
+    key: char,
+    end: Option<f64>,
+}
+
+fn presses(chart: &Chart, report: &mut Report) -> Result<Vec<Press>> {
+    let mut presses = Vec::new();
+    let mut window_phrases = 0;
+    for note in &chart.notes {
+        match note.kind {
+            NoteKind::Key(key) => presses.push(Press { time: note.time, key, end: None }),
+            NoteKind::Hold { key, end } => presses.push(Press { time: note.time, key, end: Some(end) }),
+            NoteKind::Phrase(ref phrase) => match phrase.timing {
+                PhraseTiming::PerCharacter(ref times) => {
+                    for (key, time) in phrase.text.chars().zip(times.iter()) {
+                        presses.push(Press { time: *time, key, end: None });
+                    }
+                }
+                PhraseTiming::Window { .. } => window_phrases += 1,
+            },
+        }
+    }
+    report.drop_count(window_phrases, "phrase notes without per character timing");
+    let finite = |press: &Press| press.time.is_finite() && press.end.map(f64::is_finite).unwrap_or(true);
+    if let Some(press) = presses.iter().find(|press| !finite(press)) {
+        bail!("the note of key {} has an invalid time", press.key);
+    }
+    presses.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+    Ok(presses)
+}
+
+// One lane per key of the chart, in keyboard order
+fn lanes(presses: &[Press]) -> ColumnKeys {
+    let mut keys: Vec<char> = Vec::new();
+    for press in presses {
+        if !keys.contains(&press.key) {
+            keys.push(press.key);
+        }
+    }
+    let order = |key: &char| KEYBOARD_ORDER.find(key.to_ascii_lowercase()).unwrap_or_else(|| KEYBOARD_ORDER.len());
+    keys.sort_by(|a, b| order(a).cmp(&order(b)).then(a.cmp(b)));
+    ColumnKeys(keys)
+}
+
+/// Check what of `chart` can be written as an osu!mania beatmap. Errors are
+/// things that make the export impossible, the report lists what would be
+/// dropped.
+pub fn validate(chart: &Chart, keys: Option<&ColumnKeys>) -> Result<(ColumnKeys, Report)> {
+    let mut report = Report::default();
+    let presses = presses(chart, &mut report)?;
+    let keys = match keys {
+        Some(keys) => keys.clone(),
+        None => lanes(&presses),
+    };
+    if keys.is_empty() {
+        bail!("the chart has no notes osu!mania can play");
+    }
+    if keys.len() > MAX_COLUMNS {
+        bail!("the chart uses {} keys, osu!mania has at most {} columns", keys.len(), MAX_COLUMNS);
+    }
+
+    let mut unmapped = 0;
+    let mut early = 0;
+    let mut overlapping = 0;
+    // End of the last note, per lane
+    let mut busy_until: Vec<Option<f64>> = vec![None; keys.len()];
+    for press in &presses {
+        let column = match keys.column_of(press.key) {
+            Some(column) => column,
+            None => {
+                unmapped += 1;
+                continue;
+            }
+        };
+        if press.time < 0.0 {
+            early += 1;
+            continue;
+        }
+        if busy_until[column].map(|end| press.time <= end).unwrap_or(false) {
+            overlapping += 1;
+            continue;
+        }
+        busy_until[column] = Some(press.end.unwrap_or(press.time));
+    }
+    report.drop_count(unmapped, "notes with a key outside of the lanes");
+    report.drop_count(early, "notes before the start of the audio");
+    report.drop_count(overlapping, "notes overlapping another note of the same lane");
+    if chart.timing_points.is_empty() {
+        report.drop("no timing points, 120 BPM is written".to_string());
+    }
+    Ok((keys, report))
+}
+
+/// Write `chart` as an osu!mania beatmap. Without `keys`, every key of the
+/// chart gets a lane, in keyboard order.
+pub fn export(chart: &Chart, keys: Option<&ColumnKeys>) -> Result<(String, Report)> {
+    let (keys, report) = validate(chart, keys)?;
+    let columns = keys.len();
+    let metadata = &chart.metadata;
+    let mut lines = vec![
+        "osu file format v14".to_string(),
+        String::new(),
+        "[General]".to_string(),
+        format!("AudioFilename: {}", metadata.audio),
+        "AudioLeadIn: 0".to_string(),
+        "PreviewTime: -1".to_string(),
+        format!("Mode: {}", MANIA_MODE),
+        String::new(),
+        "[Metadata]".to_string(),
+        format!("Title:{}", metadata.title),
+        format!("TitleUnicode:{}", metadata.title),
+        format!("Artist:{}", metadata.artist),
+        format!("ArtistUnicode:{}", metadata.artist),
+        format!("Creator:{}", metadata.creator),
+        format!("Version:{}", metadata.difficulty),
+        String::new(),
+        "[Difficulty]".to_string(),
+        "HPDrainRate:5".to_string(),
+        format!("CircleSize:{}", columns),
+        "OverallDifficulty:5".to_string(),
+        "ApproachRate:5".to_string(),
+        "SliderMultiplier:1.4".to_string(),
+        "SliderTickRate:1".to_string(),
+        String::new(),
+        "[Events]".to_string(),
+        String::new(),
+        "[TimingPoints]".to_string(),
+    ];
+    let default_timing = [TimingPoint { time: 0.0, bpm: 120.0, meter: 4 }];
+    let timing_points = if chart.timing_points.is_empty() {
+        &default_timing[..]
+    } else {
+        &chart.timing_points[..]
+    };
+    for point in timing_points {
+        lines.push(format!("{},{},{},1,0,100,1,0",
+            (point.time * 1000.0).round(), 60_000.0 / point.bpm, point.meter));
+    }
+    lines.push(String::new());
+    lines.push("[HitObjects]".to_string());
+
+    // Same rules as `validate`, dropped notes are not written
+    let mut busy_until: Vec<Option<f64>> = vec![None; columns];
+    for press in presses(chart, &mut Report::default())? {
+        let column = match keys.column_of(press.key) {
+            Some(column) if press.time >= 0.0 => column,
+            _ => continue,
+        };
+        if busy_until[column].map(|end| press.time <= end).unwrap_or(false) {
+            continue;
+        }
+        busy_until[column] = Some(press.end.unwrap_or(press.time));
+        let x = ((column as f64 + 0.5) * PLAYFIELD_WIDTH / columns as f64).floor();
+        let time = (press.time * 1000.0).round();
+        lines.push(match press.end {
+            Some(end) => format!("{},192,{},{},0,{}:0:0:0:0:", x, time, HOLD_NOTE, (end * 1000.0).round()),
+            None => format!("{},192,{},{},0,0:0:0:0:", x, time, HIT_CIRCLE),
+        });
+    }
+    lines.push(String::new());
+    Ok((lines.join("\r\n"), report))
+}
+
+pub fn export_file(chart: &Chart, path: &Path, keys: Option<&ColumnKeys>) -> Result<Report> {
+    let (contents, report) = export(chart, keys)?;
+    fs::write(path, contents).with_context(|| format!("Could not write {}", path.display()))?;
+    Ok(report)
+}
@@ -0,0 +1,368 @@
+// StepMania simfiles (.sm and .ssc). Every dance-single and dance-double
+// chart of the file becomes a chart, with the panels mapped onto keys.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::chart::{self, Chart, Metadata, Note, TimingPoint};
+use crate::convert::{ColumnKeys, Report};
+
+const BEATS_PER_MEASURE: f64 = 4.0;
+
+// A "#NAME:value;" tag, with the line it starts on
+struct Tag {
+    name: String,
+    value: String,
+    line: usize,
+}
+
+fn read_tags(contents: &str) -> Vec<Tag> {
+    let mut tags = Vec::new();
+    let mut current: Option<Tag> = None;
+    for (number, line) in contents.lines().enumerate() {
+        let line = match line.find("//") {
+            Some(comment) => &line[..comment],
+            None => line,
+        };
+        let mut rest = line;
+        while !rest.is_empty() {
+            match current.take() {
+                None => match rest.find('#') {
+                    Some(start) => {
+                        let body = &rest[start + 1..];
+                        let colon = body.find(':').unwrap_or(body.len());
+                        current = Some(Tag {
+                            name: body[..colon].trim().to_uppercase(),
+                            value: String::new(),
+                            line: number + 1,
+                        });
+                        rest = if colon < body.len() { &body[colon + 1..] } else { "" };
+                    }
+                    None => rest = "",
+                },
+                Some(mut tag) => match rest.find(';') {
+                    Some(end) => {
+                        tag.value.push_str(&rest[..end]);
+                        tags.push(tag);
+                        rest = &rest[end + 1..];
+                    }
+                    None => {
+                        tag.value.push_str(rest);
+                        tag.value.push('\n');
+                        current = Some(tag);
+                        rest = "";
+                    }
+                },
+            }
+        }
+        if let Some(ref mut tag) = current {
+            if !tag.value.ends_with('\n') {
+                tag.value.push('\n');
+            }
+        }
+    }
+    // Some files forget the last ';'
+    if let Some(tag) = current {
+        tags.push(tag);
+    }
+    tags
+}
+
+// "beat=value,beat=value" lists of #BPMS and #STOPS
+fn parse_beat_values(value: &str, line: usize) -> Result<Vec<(f64, f64)>> {
+    let mut pairs = Vec::new();
+    for pair in value.split(',') {
+        let pair = pair.trim();
+        if pair.is_empty() {
+            continue;
+        }
+        let mut fields = pair.splitn(2, '=');
+        let beat = fields.next().unwrap_or("").trim().parse::<f64>();
+        let value = fields.next().unwrap_or("").trim().parse::<f64>();
+        match (beat, value) {
+            (Ok(beat), Ok(value)) if beat.is_finite() && value.is_finite() => pairs.push((beat, value)),
+            _ => bail!("line {}: invalid beat value {}", line, pair),
+        }
+    }
+    pairs.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    Ok(pairs)
+}
+
+#[derive(Clone, Debug, Default)]
+struct TimingData {
+    /// Seconds between the start of the music and beat 0, negated.
+    offset: f64,
+    bpms: Vec<(f64, f64)>,
+    stops: Vec<(f64, f64)>,
+}
+
+impl TimingData {
+    // Media time of `beat`. A stop at a beat delays everything after it,
+    // but not a note on that same beat.
+    fn time_at(&self, beat: f64) -> f64 {
+        let mut time = -self.offset;
+        let mut last_beat = 0.0;
+        let mut bpm = self.bpms.first().map(|(_, bpm)| *bpm).unwrap_or(120.0);
+        for (change_beat, new_bpm) in self.bpms.iter().skip(1) {
+            if *change_beat >= beat {
+                break;
+            }
+            time += (change_beat - last_beat) * 60.0 / bpm;
+            last_beat = *change_beat;
+            bpm = *new_bpm;
+        }
+        time += (beat - last_beat) * 60.0 / bpm;
+        time + self.stops
+            .iter()
+            .filter(|(stop_beat, _)| *stop_beat < beat)
+            .map(|(_, seconds)| seconds)
+            .sum::<f64>()
+    }
+
+    fn set(&mut self, tag: &Tag, report: &mut Report) -> Result<()> {
+        match tag.name.as_str() {
+            "OFFSET" => {
+                self.offset = tag.value
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|offset: &f64| offset.is_finite())
+                    .ok_or_else(|| anyhow!("line {}: invalid offset {}", tag.line, tag.value.trim()))?;
+            }
+            "BPMS" => {
+                let bpms = parse_beat_values(&tag.value, tag.line)?;
+                let warps = bpms.iter().filter(|(_, bpm)| *bpm <= 0.0).count();
+                report.drop_count(warps, "negative or zero BPM changes (warps)");
+                self.bpms = bpms.into_iter().filter(|(_, bpm)| *bpm > 0.0).collect();
+            }
+            "STOPS" | "FREEZES" => {
+                self.stops = parse_beat_values(&tag.value, tag.line)?;
+            }
+            "DELAYS" | "WARPS" | "SPEEDS" | "SCROLLS" | "FAKES" | "TICKCOUNTS" | "COMBOS"
+                if !tag.value.trim().is_empty() =>
+            {
+                report.drop(format!("#{} timing", tag.name));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+// One #NOTES chart with what describes it
+#[derive(Clone, Debug, Default)]
+struct StepsData {
+    steps_type: String,
+    difficulty: String,
+    credit: String,
+    notes: String,
+    line: usize,
+    // .ssc charts can have their own timing
+    timing: Option<TimingData>,
+}
+
+fn columns_for(steps_type: &str) -> Option<usize> {
+    match steps_type {
+        "dance-single" => Some(4),
+        "dance-double" => Some(8),
+        _ => None,
+    }
+}
+
+// "dance-single" to "Single", for the difficulty name
+fn style_name(steps_type: &str) -> &str {
+    match steps_type {
+        "dance-double" => "Double",
+        _ => "Single",
+    }
+}
+
+fn convert_steps(
+    steps: &StepsData,
+    timing: &TimingData,
+    keys: &ColumnKeys,
+    report: &mut Report,
+) -> Result<Vec<Note>> {
+    let name = format!("{} {}", style_name(&steps.steps_type), steps.difficulty);
+    let mut notes = Vec::new();
+    // Start beat of the holds still being held, per column
+    let mut holds: Vec<Option<f64>> = vec![None; keys.len()];
+    let (mut mines, mut rolls, mut lifts, mut fakes) = (0, 0, 0, 0);
+
+    for (measure, rows) in steps.notes.split(',').enumerate() {
+        let rows: Vec<&str> = rows
+            .lines()
+            .map(|row| row.trim())
+            .filter(|row| !row.is_empty())
+            .collect();
+        for (index, row) in rows.iter().enumerate() {
+            let beat = BEATS_PER_MEASURE * (measure as f64 + index as f64 / rows.len() as f64);
+            if row.chars().count() != keys.len() {
+                bail!("{}: measure {} has a row of {} panels, expected {}",
+                    name, measure + 1, row.chars().count(), keys.len());
+            }
+            for (column, panel) in row.chars().enumerate() {
+                let key = keys.get(column).unwrap();
+                match panel {
+                    '0' => {}
+                    '1' => notes.push(Note::key(timing.time_at(beat), key)),
+                    '2' | '4' => {
+                        if panel == '4' {
+                            rolls += 1;
+                        }
+                        holds[column] = Some(beat);
+                    }
+                    '3' => match holds[column].take() {
+                        Some(start) => {
+                            notes.push(Note::hold(timing.time_at(start), key, timing.time_at(beat)))
+                        }
+                        None => report.drop(format!("{}: hold end without start at beat {}", name, beat)),
+                    },
+                    'M' => mines += 1,
+                    'L' => {
+                        lifts += 1;
+                        notes.push(Note::key(timing.time_at(beat), key));
+                    }
+                    'F' => fakes += 1,
+                    panel => report.drop(format!("{}: unknown panel '{}' at beat {}", name, panel, beat)),
+                }
+            }
+        }
+    }
+    for (column, start) in holds.iter().enumerate() {
+        if let Some(start) = start {
+            report.drop(format!("{}: hold without end in column {}, kept as a tap", name, column + 1));
+            notes.push(Note::key(timing.time_at(*start), keys.get(column).unwrap()));
+        }
+    }
+    report.drop_count(mines, &format!("mines in {}", name));
+    report.drop_count(fakes, &format!("fake notes in {}", name));
+    report.drop_count(rolls, &format!("rolls in {} (played as holds)", name));
+    report.drop_count(lifts, &format!("lifts in {} (played as taps)", name));
+    notes.sort_by(|a, b| a.time.partial_cmp(&b.time).unwrap());
+    Ok(notes)
+}
+
+/// Convert every dance-single and dance-double chart of a simfile. Without
+/// `keys`, the default layout for 4 or 8 columns is used.
+pub fn import(contents: &str, keys: Option<ColumnKeys>) -> Result<(Vec<Chart>, Report)> {
+    let mut report = Report::default();
+    let mut metadata = Metadata::default();
+    let mut timing = TimingData::default();
+    let mut all_steps: Vec<StepsData> = Vec::new();
+    // .ssc files describe charts with several tags after #NOTEDATA
+    let mut in_note_data = false;
+
+    for tag in read_tags(contents) {
+        let value = tag.value.trim().to_string();
+        if in_note_data {
+            let steps = all_steps.last_mut().unwrap();
+            match tag.name.as_str() {
+                "STEPSTYPE" => steps.steps_type = value,
+                "DIFFICULTY" => steps.difficulty = value,
+                "CREDIT" => steps.credit = value,
+                "NOTES" | "NOTES2" => {
+                    steps.notes = tag.value.clone();
+                    steps.line = tag.line;
+                }
+                "OFFSET" | "BPMS" | "STOPS" | "DELAYS" | "WARPS" | "SPEEDS" | "SCROLLS" | "FAKES" => {
+                    let mut chart_timing = steps.timing.clone().unwrap_or_else(|| timing.clone());
+                    chart_timing.set(&tag, &mut report)?;
+                    steps.timing = Some(chart_timing);
+                }
+                "NOTEDATA" => all_steps.push(StepsData::default()),
+                _ => {}
+            }
+            continue;
+        }
+        match tag.name.as_str() {
+            "TITLE" => metadata.title = value,
+            "ARTIST" => metadata.artist = value,
+            "CREDIT" => metadata.creator = value,
+            "MUSIC" => metadata.audio = value,
+            "NOTEDATA" => {
+                in_note_data = true;
+                all_steps.push(StepsData::default());
+            }
+            // .sm: "type:author:difficulty:meter:radar:notes"
+            "NOTES" => {
+                let fields: Vec<&str> = tag.value.splitn(6, ':').collect();
+                if fields.len() != 6 {
+                    bail!("line {}: #NOTES needs 6 fields, found {}", tag.line, fields.len());
+                }
+                all_steps.push(StepsData {
+                    steps_type: fields[0].trim().to_string(),
+                    credit: fields[1].trim().to_string(),
+                    difficulty: fields[2].trim().to_string(),
+                    notes: fields[5].to_string(),
+                    line: tag.line,
+                    timing: None,
+                });
+            }
+            "BGCHANGES" | "FGCHANGES" | "ATTACKS" | "KEYSOUNDS" => {
+                if !value.is_empty() {
+                    report.drop(format!("#{}", tag.name));
+                }
+            }
+            _ => timing.set(&tag, &mut report)?,
+        }
+    }
+    let mut charts = Vec::new();
+    for steps in &all_steps {
+        let columns = match columns_for(&steps.steps_type) {
+            Some(columns) => columns,
+            None => {
+                report.drop(format!("{} {} chart", steps.steps_type, steps.difficulty));
+                continue;
+            }
+        };
+        let keys = match keys {
+            Some(ref keys) if keys.len() >= columns => ColumnKeys(keys.0[..columns].to_vec()),
+            Some(ref keys) => bail!("{} keys given for {} panels", keys.len(), columns),
+            None => ColumnKeys::default_for(columns).unwrap(),
+        };
+        // .ssc charts may have their own timing, and then the song none
+        let chart_timing = steps.timing.as_ref().unwrap_or(&timing);
+        if chart_timing.bpms.is_empty() {
+            bail!("no #BPMS for the {} {} chart", steps.steps_type, steps.difficulty);
+        }
+        let notes = convert_steps(steps, chart_timing, &keys, &mut report)
+            .with_context(|| format!("#NOTES on line {}", steps.line))?;
+
+        let mut chart = Chart::new(metadata.clone());
+        chart.metadata.difficulty = format!("{} {}", style_name(&steps.steps_type), steps.difficulty);
+        if !steps.credit.is_empty() {
+            chart.metadata.creator = steps.credit.clone();
+        }
+        chart.timing_points = chart_timing
+            .bpms
+            .iter()
+            .map(|(beat, bpm)| TimingPoint {
+                time: chart_timing.time_at(*beat),
+                bpm: *bpm,
+                meter: 4,
+            })
+            .collect();
+        chart.notes = notes;
+        charts.push(chart);
+    }
+    Ok((charts, report))
+}
+
+/// Convert the simfile at `path` and save the charts next to its music.
+/// Returns where the charts were saved.
+pub fn import_file(path: &Path, keys: Option<ColumnKeys>) -> Result<(Vec<PathBuf>, Report)> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read {}", path.display()))?;
+    let (charts, report) = import(&contents, keys)
+        .with_context(|| format!("Could not convert {}", path.display()))?;
+    let mut paths = Vec::new();
+    for chart in charts {
+        let audio = path.with_file_name(&chart.metadata.audio);
+        let chart_path = chart::chart_path(&audio, &chart.metadata.difficulty);
+        chart.save(&chart_path)?;
+        paths.push(chart_path);
+    }
+    Ok((paths, report))
+}
@@ -0,0 +1,24 @@
+// Exit codes of the command line, and which errors end with which.
+use std::io;
+
+pub const SUCCESS: i32 = 0;
+/// The command ran and found problems.
+pub const FAILURE: i32 = 1;
+/// The command could not run: wrong arguments, missing files.
+pub const USAGE: i32 = 2;
+
+/// Exit code of a command that failed with `err`. A file that does not exist
+/// is a wrong argument, anything else a failure.
+pub fn code(err: &anyhow::Error) -> i32 {
+    let missing = err.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .map(|err| err.kind() == io::ErrorKind::NotFound)
+            .unwrap_or(false)
+    });
+    if missing {
+        USAGE
+    } else {
+        FAILURE
+    }
+}
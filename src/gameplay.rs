@@ -0,0 +1,322 @@
+// Playing a chart, without the media or the window: the frontend gives the
+// media time of every frame and of every key pressed and released, and reads
+// back the phrase being typed, the judgements and the score.
+use crate::chart::{Chart, Note, NoteKind};
+use crate::judgement::{Judgement, Windows};
+use crate::practice;
+use crate::replay::{self, Input, KeyAction, Replay};
+use crate::score::Score;
+use crate::typing::PhraseInput;
+
+/// A judgement given while playing.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Judged {
+    /// Index of the note in the chart notes.
+    pub note: usize,
+    pub judgement: Judgement,
+    /// Seconds between the press and its target, negative when early.
+    /// `None` for misses, ends of hold notes and phrase characters.
+    pub offset: Option<f64>,
+    /// Media time the judgement was given at.
+    pub time: f64,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum NoteState {
+    Pending,
+    // Hold note whose head was hit and that is still held
+    Held,
+    // Judged, or a phrase that was typed
+    Done,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Gameplay {
+    pub chart: Option<Chart>,
+    pub windows: Windows,
+    pub score: Score,
+    /// Names of the modifiers the chart is played with.
+    pub mods: Vec<String>,
+    // Index in the chart notes of the phrase being typed, and what was typed
+    current_phrase: Option<(usize, PhraseInput)>,
+    // State of each note, by index in the chart notes. Notes past the end
+    // are pending.
+    states: Vec<NoteState>,
+    judged: Vec<Judged>,
+    // Every key pressed and released, for the replay
+    inputs: Vec<Input>,
+}
+
+impl Gameplay {
+    pub fn new(chart: Option<Chart>) -> Gameplay {
+        Gameplay {
+            chart,
+            ..Gameplay::default()
+        }
+    }
+
+    /// Play at `rate` times the normal speed, before the first key: the
+    /// windows are scaled to stay as wide in real time, and a play slower
+    /// than normal is marked as practice. Setting another rate replaces the
+    /// last one.
+    pub fn set_rate(&mut self, rate: f64) {
+        self.windows = Windows::default().scaled(rate);
+        self.mods.retain(|name| practice::mod_rate(name).is_none());
+        if rate != 1.0 {
+            self.mods.push(practice::mod_name(rate));
+        }
+    }
+
+    /// The rate the chart is played at, from the mods.
+    pub fn rate(&self) -> f64 {
+        practice::rate(&self.mods)
+    }
+
+    /// The phrase note being typed, with its index in the chart notes.
+    pub fn current_phrase(&self) -> Option<(usize, &PhraseInput)> {
+        self.current_phrase.as_ref().map(|(index, input)| (*index, input))
+    }
+
+    /// Every judgement given so far, in order.
+    pub fn judgements(&self) -> &[Judged] {
+        &self.judged
+    }
+
+    /// Replay of the play so far, `None` without a chart.
+    pub fn replay(&self) -> Option<Replay> {
+        self.chart.as_ref().map(|chart| {
+            Replay::new(chart, self.windows, self.mods.clone(), self.score.clone(), self.inputs.clone())
+        })
+    }
+
+    /// Whether the note at `index` was not judged yet, or is a hold note
+    /// still held.
+    pub fn is_pending(&self, index: usize) -> bool {
+        self.state(index) != NoteState::Done
+    }
+
+    /// Whether the note at `index` is a hold note being held.
+    pub fn is_held(&self, index: usize) -> bool {
+        self.state(index) == NoteState::Held
+    }
+
+    /// Judge the notes whose time is over: misses, hold notes held to the
+    /// end and phrases not typed in time. Then start the phrase under the
+    /// media time.
+    pub fn update(&mut self, time: f64) {
+        let chart = match self.chart {
+            Some(ref chart) => chart,
+            None => return,
+        };
+        // Judged in the order they expired, so that the combo does not
+        // depend on how often this is called
+        let mut expired = Vec::new();
+        for (index, note) in chart.notes.iter().enumerate() {
+            let state = self.state(index);
+            let expiry = match (state, &note.kind) {
+                (NoteState::Done, _) => continue,
+                (NoteState::Held, _) => note.end_time(),
+                (NoteState::Pending, NoteKind::Phrase(_)) => note.end_time() + self.windows.good,
+                (NoteState::Pending, _) => note.time + self.windows.good,
+            };
+            if time > expiry || (state == NoteState::Held && time >= expiry) {
+                expired.push((expiry, index));
+            }
+        }
+        expired.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        for (expiry, index) in expired {
+            self.expire(index, expiry);
+        }
+
+        // Phrases are typed from the start of their early window
+        if self.current_phrase.is_none() {
+            let chart = self.chart.as_ref().unwrap();
+            let under = chart.notes.iter().enumerate().find_map(|(index, note)| match note.kind {
+                NoteKind::Phrase(ref phrase)
+                    if self.state(index) == NoteState::Pending
+                        && note.time - self.windows.good <= time
+                        && time <= note.end_time() =>
+                {
+                    Some((index, PhraseInput::new(phrase)))
+                }
+                _ => None,
+            });
+            self.current_phrase = under;
+        }
+    }
+
+    /// The media is over: every note still pending or held is judged as if
+    /// its windows had passed, as the replay of the play will be.
+    pub fn finish(&mut self) {
+        self.update(f64::INFINITY);
+    }
+
+    fn expire(&mut self, index: usize, time: f64) {
+        let chart = self.chart.as_ref().unwrap();
+        match (self.state(index), &chart.notes[index].kind) {
+            (NoteState::Pending, NoteKind::Key(_)) => self.judge(index, Judgement::Miss, None, time),
+            // Missing the head misses the whole hold
+            (NoteState::Pending, NoteKind::Hold { .. }) => {
+                self.judge(index, Judgement::Miss, None, time);
+                self.judge(index, Judgement::Miss, None, time);
+            }
+            (NoteState::Pending, NoteKind::Phrase(_)) => self.finish_phrase(index, time),
+            (NoteState::Held, _) => self.judge(index, Judgement::Perfect, None, time),
+            (NoteState::Done, _) => {}
+        }
+        self.set_state(index, NoteState::Done);
+    }
+
+    // Score the phrase at `index` with what was typed of it, nothing when it
+    // was never started
+    fn finish_phrase(&mut self, index: usize, time: f64) {
+        let note = &self.chart.as_ref().unwrap().notes[index];
+        let phrase = match note.kind {
+            NoteKind::Phrase(ref phrase) => phrase,
+            _ => return,
+        };
+        let input = match self.current_phrase {
+            Some((current, _)) if current == index => self.current_phrase.take().unwrap().1,
+            _ => PhraseInput::new(phrase),
+        };
+        let result = input.result(phrase, note.time, &self.windows);
+        self.score.add_phrase(&result);
+        self.judged.extend(result.judgements.iter().map(|judgement| Judged {
+            note: index,
+            judgement: *judgement,
+            offset: None,
+            time,
+        }));
+        self.set_state(index, NoteState::Done);
+    }
+
+    /// A key pressed at media time `time`. It hits the earliest pending key
+    /// or hold note of that key within the windows, or else is typed in the
+    /// current phrase. Times are rounded like in replays.
+    pub fn key_down(&mut self, key: char, time: f64) {
+        let time = replay::quantize(time);
+        self.inputs.push(Input { time, key, action: KeyAction::Down });
+        self.update(time);
+        let hit = match self.chart {
+            Some(ref chart) => chart
+                .notes
+                .iter()
+                .enumerate()
+                .filter(|(index, _)| self.state(*index) == NoteState::Pending)
+                .filter_map(|(index, note)| match note.kind {
+                    NoteKind::Key(k) | NoteKind::Hold { key: k, .. } if k == key => {
+                        let offset = time - note.time;
+                        self.windows.judge(offset).map(|judgement| (index, judgement, offset))
+                    }
+                    _ => None,
+                })
+                .next(),
+            None => None,
+        };
+        match hit {
+            Some((index, judgement, offset)) => {
+                self.judge(index, judgement, Some(offset), time);
+                let state = match self.chart.as_ref().unwrap().notes[index].kind {
+                    NoteKind::Hold { .. } => NoteState::Held,
+                    _ => NoteState::Done,
+                };
+                self.set_state(index, state);
+            }
+            None => self.type_char(key, time),
+        }
+    }
+
+    /// A key released at media time `time`, ending the hold note it holds.
+    /// Releasing before the end is judged like a press, and a miss outside
+    /// the windows.
+    pub fn key_up(&mut self, key: char, time: f64) {
+        let time = replay::quantize(time);
+        self.inputs.push(Input { time, key, action: KeyAction::Up });
+        self.update(time);
+        let held = match self.chart {
+            Some(ref chart) => chart.notes.iter().enumerate().find_map(|(index, note)| match note.kind {
+                NoteKind::Hold { key: k, end } if k == key && self.state(index) == NoteState::Held => {
+                    Some((index, end))
+                }
+                _ => None,
+            }),
+            None => None,
+        };
+        if let Some((index, end)) = held {
+            let judgement = self.windows.judge((time - end).min(0.0)).unwrap_or(Judgement::Miss);
+            self.judge(index, judgement, None, time);
+            self.set_state(index, NoteState::Done);
+        }
+    }
+
+    // A character typed in the current phrase, which is scored as soon as
+    // it is fully typed
+    fn type_char(&mut self, character: char, time: f64) {
+        let complete = match self.current_phrase {
+            Some((index, ref mut input)) => {
+                match character {
+                    // Backspace
+                    '\u{8}' => input.backspace(),
+                    character if character.is_control() => {}
+                    character => {
+                        input.type_char(character, time);
+                    }
+                }
+                Some(index).filter(|_| input.is_complete())
+            }
+            None => None,
+        };
+        if let Some(index) = complete {
+            self.finish_phrase(index, time);
+        }
+    }
+
+    /// A key pressed or released, like in a replay.
+    pub fn input(&mut self, input: &Input) {
+        match input.action {
+            KeyAction::Down => self.key_down(input.key, input.time),
+            KeyAction::Up => self.key_up(input.key, input.time),
+        }
+    }
+
+    /// Add a note while playing, unless the chart already has it. Returns
+    /// its index in the chart notes.
+    pub fn add_note(&mut self, note: Note) -> Option<usize> {
+        let chart = self.chart.get_or_insert_with(Chart::default);
+        if chart.notes.contains(&note) {
+            return None;
+        }
+        let index = chart.add_note(note);
+        // Notes after the new one moved one place
+        if index < self.states.len() {
+            self.states.insert(index, NoteState::Pending);
+        }
+        for judged in &mut self.judged {
+            if judged.note >= index {
+                judged.note += 1;
+            }
+        }
+        if let Some((ref mut current, _)) = self.current_phrase {
+            if *current >= index {
+                *current += 1;
+            }
+        }
+        Some(index)
+    }
+
+    fn state(&self, index: usize) -> NoteState {
+        self.states.get(index).cloned().unwrap_or(NoteState::Pending)
+    }
+
+    fn set_state(&mut self, index: usize, state: NoteState) {
+        if self.states.len() <= index {
+            self.states.resize(index + 1, NoteState::Pending);
+        }
+        self.states[index] = state;
+    }
+
+    fn judge(&mut self, note: usize, judgement: Judgement, offset: Option<f64>, time: f64) {
+        self.score.add_key(judgement);
+        self.judged.push(Judged { note, judgement, offset, time });
+    }
+}
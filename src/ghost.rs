@@ -0,0 +1,54 @@
+// Racing a replay: it is played in lock-step with the live play, on the same
+// chart, and both are compared as they go.
+use std::cmp::Ordering;
+
+use anyhow::{bail, Result};
+
+use crate::chart::Chart;
+use crate::gameplay::Gameplay;
+use crate::replay::{Playback, Replay};
+
+pub struct Ghost {
+    playback: Playback,
+    pub gameplay: Gameplay,
+}
+
+impl Ghost {
+    pub fn new(replay: Replay, chart: &Chart) -> Result<Ghost> {
+        if chart.hash() != replay.chart_hash {
+            bail!("the ghost replay was not recorded on this chart");
+        }
+        let playback = Playback::new(replay);
+        let gameplay = playback.start(chart.clone());
+        Ok(Ghost { playback, gameplay })
+    }
+
+    /// Play the ghost up to the media time of the live play.
+    pub fn update(&mut self, time: f64) {
+        self.playback.seek(&mut self.gameplay, time);
+    }
+
+    /// Points of the live play ahead of the ghost, negative when behind.
+    pub fn lead(&self, live: &Gameplay) -> i64 {
+        live.score.points as i64 - self.gameplay.score.points as i64
+    }
+
+    /// How the live play did on the note at `index` compared to the ghost,
+    /// once both judged all of it. `Greater` means better than the ghost.
+    pub fn compare_note(&self, live: &Gameplay, index: usize) -> Option<Ordering> {
+        if live.is_pending(index) || self.gameplay.is_pending(index) {
+            return None;
+        }
+        note_weight(live, index).partial_cmp(&note_weight(&self.gameplay, index))
+    }
+}
+
+// Sum of the judgement weights given to a note
+fn note_weight(gameplay: &Gameplay, index: usize) -> f64 {
+    gameplay
+        .judgements()
+        .iter()
+        .filter(|judged| judged.note == index)
+        .map(|judged| judged.judgement.weight())
+        .sum()
+}
@@ -0,0 +1,227 @@
+// Note highway layout: where the judgement line, the notes on their way, the
+// key of every lane and the flash of the last judgement are at a media time.
+// Everything is a rectangle or a line of text, in the coordinates of the
+// frontend (origin in the middle, y going up), for it to draw in one pass.
+use crate::chart::{Chart, NoteKind};
+use crate::gameplay::Gameplay;
+use crate::judgement::Judgement;
+use crate::profile::Profile;
+
+// In pixels
+pub const LANE_WIDTH: f64 = 48.0;
+pub const NOTE_HEIGHT: f64 = 8.0;
+pub const JUDGEMENT_LINE_MARGIN: f64 = 40.0;
+const LINE_HEIGHT: f64 = 2.0;
+const FLASH_HEIGHT: f64 = 24.0;
+// Between two notes side by side
+const NOTE_GAP: f64 = 4.0;
+/// How long a judgement stays on screen, in seconds.
+pub const JUDGEMENT_SHOWN: f64 = 0.5;
+
+/// A rectangle from its centre and size.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Rect {
+    pub x: f64,
+    pub y: f64,
+    pub w: f64,
+    pub h: f64,
+}
+
+impl Rect {
+    pub fn top(&self) -> f64 {
+        self.y + self.h / 2.0
+    }
+
+    pub fn bottom(&self) -> f64 {
+        self.y - self.h / 2.0
+    }
+}
+
+/// What a rectangle is, for the frontend to colour it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Paint {
+    Line,
+    Note,
+    Hold,
+    /// Over the lane of the last note judged.
+    Flash(Judgement),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Quad {
+    pub rect: Rect,
+    pub paint: Paint,
+    /// From 1, opaque, to 0 as effects fade out.
+    pub alpha: f64,
+}
+
+/// What a label is, for the frontend to colour it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ink {
+    Key,
+    Judgement(Judgement),
+    Combo,
+}
+
+/// A line of text centred on `x`, `y`, `size` pixels high.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Label {
+    pub text: String,
+    pub x: f64,
+    pub y: f64,
+    pub size: f64,
+    pub ink: Ink,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frame {
+    pub quads: Vec<Quad>,
+    pub labels: Vec<Label>,
+}
+
+/// The keys of the chart in lane order.
+pub fn lanes(chart: &Chart) -> Vec<char> {
+    let mut lanes: Vec<char> = chart
+        .notes
+        .iter()
+        .filter_map(|note| match note.kind {
+            NoteKind::Key(key) | NoteKind::Hold { key, .. } => Some(key),
+            NoteKind::Phrase(_) => None,
+        })
+        .collect();
+    lanes.sort();
+    lanes.dedup();
+    lanes
+}
+
+/// The highway in `area` at media `time`, scrolled and drawn ahead as the
+/// profile plays. Both are in real time, and so scaled by the rate of the
+/// play in media time.
+pub fn frame(gameplay: &Gameplay, profile: &Profile, area: Rect, time: f64) -> Frame {
+    let mut frame = Frame::default();
+    let chart = match gameplay.chart {
+        Some(ref chart) => chart,
+        None => return frame,
+    };
+    let lanes = lanes(chart);
+    if lanes.is_empty() {
+        return frame;
+    }
+    let lane_width = (area.w / lanes.len() as f64).min(LANE_WIDTH);
+    let lane_x = |key: char| {
+        let lane = lanes.iter().position(|k| *k == key).unwrap_or(0) as f64;
+        area.x + (lane - (lanes.len() as f64 - 1.0) / 2.0) * lane_width
+    };
+    let rate = gameplay.rate();
+    // Pixels per media second
+    let scroll_speed = profile.scroll_speed / rate;
+    let line_y = area.bottom() + JUDGEMENT_LINE_MARGIN;
+    let look_ahead = (area.top() - line_y) / scroll_speed;
+
+    frame.quads.push(Quad {
+        rect: Rect {
+            x: area.x,
+            y: line_y,
+            w: lane_width * lanes.len() as f64,
+            h: LINE_HEIGHT,
+        },
+        paint: Paint::Line,
+        alpha: 1.0,
+    });
+    for key in &lanes {
+        // The key to press, when the profile plays this one with another
+        let pressed = profile
+            .bindings
+            .iter()
+            .find(|(_, played)| played == key)
+            .map_or(*key, |(pressed, _)| *pressed);
+        let text = match pressed {
+            ' ' => "space".to_string(),
+            key => key.to_string(),
+        };
+        frame.labels.push(Label {
+            text,
+            x: lane_x(*key),
+            y: line_y - JUDGEMENT_LINE_MARGIN / 2.0,
+            size: 14.0,
+            ink: Ink::Key,
+        });
+    }
+
+    // Notes hit leave the highway, missed ones go on until the line. They are
+    // drawn ahead by the time the picture takes to show.
+    let time = time + profile.visual_offset * rate;
+    for (index, note) in chart.notes.iter().enumerate() {
+        if !gameplay.is_pending(index)
+            || note.end_time() < time - gameplay.windows.good
+            || note.time > time + look_ahead
+        {
+            continue;
+        }
+        let (key, start, end) = match note.kind {
+            NoteKind::Key(key) => (key, note.time, note.time),
+            NoteKind::Hold { key, end } => (key, note.time, end),
+            NoteKind::Phrase(_) => continue,
+        };
+        // A held note is eaten by the line
+        let start = if gameplay.is_held(index) {
+            start.max(time)
+        } else {
+            start
+        };
+        let bottom = line_y + (start - time) * scroll_speed;
+        let top = (line_y + (end - time) * scroll_speed).min(area.top());
+        let height = (top - bottom).max(NOTE_HEIGHT);
+        frame.quads.push(Quad {
+            rect: Rect {
+                x: lane_x(key),
+                y: bottom + height / 2.0,
+                w: lane_width - NOTE_GAP,
+                h: height,
+            },
+            paint: if end > start { Paint::Hold } else { Paint::Note },
+            alpha: 1.0,
+        });
+    }
+
+    let last = gameplay
+        .judgements()
+        .last()
+        .filter(|judged| judged.time <= time && time - judged.time < JUDGEMENT_SHOWN);
+    if let Some(judged) = last {
+        let alpha = 1.0 - (time - judged.time) / JUDGEMENT_SHOWN;
+        let key = chart.notes.get(judged.note).and_then(|note| match note.kind {
+            NoteKind::Key(key) | NoteKind::Hold { key, .. } => Some(key),
+            NoteKind::Phrase(_) => None,
+        });
+        if let Some(key) = key {
+            frame.quads.push(Quad {
+                rect: Rect {
+                    x: lane_x(key),
+                    y: line_y + FLASH_HEIGHT / 2.0,
+                    w: lane_width - NOTE_GAP,
+                    h: FLASH_HEIGHT,
+                },
+                paint: Paint::Flash(judged.judgement),
+                alpha,
+            });
+        }
+        frame.labels.push(Label {
+            text: judged.judgement.name().to_string(),
+            x: area.x,
+            y: line_y + 60.0,
+            size: 24.0,
+            ink: Ink::Judgement(judged.judgement),
+        });
+    }
+    if gameplay.score.combo > 1 {
+        frame.labels.push(Label {
+            text: format!("{} combo", gameplay.score.combo),
+            x: area.x,
+            y: line_y + 90.0,
+            size: 16.0,
+            ink: Ink::Combo,
+        });
+    }
+    frame
+}
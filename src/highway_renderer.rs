@@ -0,0 +1,315 @@
+// Draws the note highway on top of the conrod UI in a single instanced draw
+// call: every note, line, flash and glyph is one instance of the same quad,
+// glyphs sampling a texture atlas of the printable ASCII characters
+// rasterised once from the UI font. Dense charts cost one buffer upload a
+// frame instead of a widget per note.
+use std::borrow::Cow;
+
+use conrod_core::color::{self, Color};
+use conrod_core::text::{rt, Font, Scale};
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{implement_vertex, program, uniform, Surface};
+
+use mechanical::highway::{Frame, Ink, Paint};
+use mechanical::judgement::Judgement;
+
+use crate::media_player::media_player::Palette;
+
+/// Glyphs are rasterised at this height, in pixels, and scaled from there.
+const ATLAS_SCALE: f32 = 32.0;
+const ATLAS_WIDTH: u32 = 512;
+// Between glyphs in the atlas, so that filtering does not bleed
+const ATLAS_PADDING: u32 = 2;
+
+#[derive(Copy, Clone)]
+struct Corner {
+    corner: [f32; 2],
+}
+implement_vertex!(Corner, corner);
+
+#[derive(Copy, Clone)]
+struct Instance {
+    center: [f32; 2],
+    size: [f32; 2],
+    color: [f32; 4],
+    // Atlas coordinates of the bottom left and top right corners, all zero
+    // for a plain rectangle
+    uv: [f32; 4],
+}
+implement_vertex!(Instance, center, size, color, uv);
+
+const VERTEX_SHADER: &str = "
+    #version 140
+
+    uniform vec2 screen;
+
+    in vec2 corner;
+    in vec2 center;
+    in vec2 size;
+    in vec4 color;
+    in vec4 uv;
+
+    out vec4 v_color;
+    out vec2 v_uv;
+    out float v_textured;
+
+    void main() {
+        gl_Position = vec4((center + corner * size) / (screen / 2.0), 0.0, 1.0);
+        v_color = color;
+        v_uv = mix(uv.xy, uv.zw, corner + 0.5);
+        v_textured = uv.z > uv.x ? 1.0 : 0.0;
+    }
+";
+
+const FRAGMENT_SHADER: &str = "
+    #version 140
+
+    uniform sampler2D atlas;
+
+    in vec4 v_color;
+    in vec2 v_uv;
+    in float v_textured;
+
+    out vec4 f_color;
+
+    void main() {
+        float coverage = v_textured > 0.5 ? texture(atlas, v_uv).r : 1.0;
+        f_color = vec4(v_color.rgb, v_color.a * coverage);
+    }
+";
+
+// Where a glyph is in the atlas and how it sits on the baseline, in pixels
+// at `ATLAS_SCALE`
+#[derive(Copy, Clone, Default)]
+struct Glyph {
+    uv: [f32; 4],
+    // Top left corner from the pen, y going down
+    offset: [f32; 2],
+    size: [f32; 2],
+    advance: f32,
+}
+
+struct Atlas {
+    texture: Texture2d,
+    // Printable ASCII, from the space
+    glyphs: Vec<Glyph>,
+    ascent: f32,
+    descent: f32,
+}
+
+impl Atlas {
+    fn new(display: &glium::Display, font: &Font) -> anyhow::Result<Atlas> {
+        let scale = Scale::uniform(ATLAS_SCALE);
+        let metrics = font.v_metrics(scale);
+        // Shelves of glyphs, one row after the other
+        let mut placed = Vec::new();
+        let (mut x, mut y, mut row_height) = (ATLAS_PADDING, ATLAS_PADDING, 0);
+        for character in ' '..='~' {
+            let glyph = font.glyph(character).scaled(scale);
+            let advance = glyph.h_metrics().advance_width;
+            let glyph = glyph.positioned(rt::point(0.0, 0.0));
+            let bounds = glyph.pixel_bounding_box();
+            if let Some(bounds) = bounds {
+                let (w, h) = (bounds.width() as u32, bounds.height() as u32);
+                if x + w + ATLAS_PADDING > ATLAS_WIDTH {
+                    x = ATLAS_PADDING;
+                    y += row_height + ATLAS_PADDING;
+                    row_height = 0;
+                }
+                placed.push((glyph, advance, Some((x, y, bounds))));
+                x += w + ATLAS_PADDING;
+                row_height = row_height.max(h);
+            } else {
+                placed.push((glyph, advance, None));
+            }
+        }
+        let height = (y + row_height + ATLAS_PADDING).next_power_of_two();
+
+        let mut pixels = vec![0u8; (ATLAS_WIDTH * height) as usize];
+        let mut glyphs = Vec::new();
+        for (glyph, advance, place) in placed {
+            let (x, y, bounds) = match place {
+                Some(place) => place,
+                None => {
+                    glyphs.push(Glyph {
+                        advance,
+                        ..Glyph::default()
+                    });
+                    continue;
+                }
+            };
+            glyph.draw(|gx, gy, coverage| {
+                pixels[((y + gy) * ATLAS_WIDTH + x + gx) as usize] = (coverage * 255.0) as u8;
+            });
+            let (w, h) = (bounds.width() as f32, bounds.height() as f32);
+            let (u0, u1) = (x as f32 / ATLAS_WIDTH as f32, (x as f32 + w) / ATLAS_WIDTH as f32);
+            let (v_top, v_bottom) = (y as f32 / height as f32, (y as f32 + h) / height as f32);
+            glyphs.push(Glyph {
+                uv: [u0, v_bottom, u1, v_top],
+                offset: [bounds.min.x as f32, bounds.min.y as f32],
+                size: [w, h],
+                advance,
+            });
+        }
+        let image = RawImage2d {
+            data: Cow::Owned(pixels),
+            width: ATLAS_WIDTH,
+            height,
+            format: ClientFormat::U8,
+        };
+        let texture = Texture2d::with_format(display, image, UncompressedFloatFormat::U8, MipmapsOption::NoMipmap)?;
+        Ok(Atlas {
+            texture,
+            glyphs,
+            ascent: metrics.ascent,
+            descent: metrics.descent,
+        })
+    }
+
+    fn glyph(&self, character: char) -> Glyph {
+        let index = |character: char| (character as usize).checked_sub(' ' as usize);
+        index(character)
+            .and_then(|index| self.glyphs.get(index))
+            .or_else(|| self.glyphs.get(index('?').unwrap()))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+pub struct HighwayRenderer {
+    program: glium::Program,
+    corners: glium::VertexBuffer<Corner>,
+    instances: glium::VertexBuffer<Instance>,
+    atlas: Atlas,
+    // Built again every frame, kept for its allocation
+    staged: Vec<Instance>,
+}
+
+impl HighwayRenderer {
+    pub fn new(display: &glium::Display, font: &Font) -> anyhow::Result<HighwayRenderer> {
+        let program = program!(display, 140 => { vertex: VERTEX_SHADER, fragment: FRAGMENT_SHADER })?;
+        let corners = glium::VertexBuffer::new(
+            display,
+            &[
+                Corner { corner: [-0.5, -0.5] },
+                Corner { corner: [0.5, -0.5] },
+                Corner { corner: [-0.5, 0.5] },
+                Corner { corner: [0.5, 0.5] },
+            ],
+        )?;
+        let instances = glium::VertexBuffer::empty_dynamic(display, 1024)?;
+        Ok(HighwayRenderer {
+            program,
+            corners,
+            instances,
+            atlas: Atlas::new(display, font)?,
+            staged: Vec::new(),
+        })
+    }
+
+    /// Draws `frame` over `target`, a window of `window` logical pixels.
+    pub fn draw<S: Surface>(
+        &mut self,
+        display: &glium::Display,
+        target: &mut S,
+        frame: &Frame,
+        palette: &Palette,
+        window: [f64; 2],
+    ) -> anyhow::Result<()> {
+        self.stage(frame, palette);
+        if self.staged.is_empty() {
+            return Ok(());
+        }
+        if self.instances.len() < self.staged.len() {
+            self.instances = glium::VertexBuffer::empty_dynamic(display, self.staged.len().next_power_of_two())?;
+        }
+        let instances = self.instances.slice(0..self.staged.len()).unwrap();
+        instances.write(&self.staged);
+        let per_instance = instances
+            .per_instance()
+            .map_err(|_| anyhow::anyhow!("instanced drawing is not supported"))?;
+        let uniforms = uniform! {
+            screen: [window[0] as f32, window[1] as f32],
+            atlas: self.atlas.texture.sampled()
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .minify_filter(MinifySamplerFilter::Linear),
+        };
+        let parameters = glium::DrawParameters {
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        target.draw(
+            (&self.corners, per_instance),
+            glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip),
+            &self.program,
+            &uniforms,
+            &parameters,
+        )?;
+        Ok(())
+    }
+
+    // The instances of the quads, then of the glyphs of the labels over them
+    fn stage(&mut self, frame: &Frame, palette: &Palette) {
+        self.staged.clear();
+        for quad in &frame.quads {
+            let color = match quad.paint {
+                Paint::Line | Paint::Note => palette.text,
+                Paint::Hold => color::LIGHT_BLUE,
+                Paint::Flash(judgement) => judgement_color(judgement),
+            };
+            self.staged.push(Instance {
+                center: [quad.rect.x as f32, quad.rect.y as f32],
+                size: [quad.rect.w as f32, quad.rect.h as f32],
+                color: linear(color, quad.alpha),
+                uv: [0.0; 4],
+            });
+        }
+        for label in &frame.labels {
+            let color = match label.ink {
+                Ink::Key => palette.dim_text,
+                Ink::Judgement(judgement) => judgement_color(judgement),
+                Ink::Combo => palette.text,
+            };
+            let color = linear(color, 1.0);
+            let scale = label.size as f32 / ATLAS_SCALE;
+            let width: f32 = label
+                .text
+                .chars()
+                .map(|character| self.atlas.glyph(character).advance)
+                .sum();
+            let mut pen = label.x as f32 - width * scale / 2.0;
+            let baseline = label.y as f32 - (self.atlas.ascent + self.atlas.descent) * scale / 2.0;
+            for character in label.text.chars() {
+                let glyph = self.atlas.glyph(character);
+                if glyph.size[0] > 0.0 {
+                    self.staged.push(Instance {
+                        center: [
+                            pen + (glyph.offset[0] + glyph.size[0] / 2.0) * scale,
+                            baseline - (glyph.offset[1] + glyph.size[1] / 2.0) * scale,
+                        ],
+                        size: [glyph.size[0] * scale, glyph.size[1] * scale],
+                        color,
+                        uv: glyph.uv,
+                    });
+                }
+                pen += glyph.advance * scale;
+            }
+        }
+    }
+}
+
+pub fn judgement_color(judgement: Judgement) -> Color {
+    match judgement {
+        Judgement::Perfect => color::YELLOW,
+        Judgement::Great => color::GREEN,
+        Judgement::Good => color::LIGHT_BLUE,
+        Judgement::Miss => color::RED,
+    }
+}
+
+// The colour as the conrod renderer outputs it, faded
+fn linear(color: Color, alpha: f64) -> [f32; 4] {
+    let [r, g, b, a] = conrod_glium::gamma_srgb_to_linear(color.to_fsa());
+    [r, g, b, a * alpha as f32]
+}
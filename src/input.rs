@@ -0,0 +1,54 @@
+// Keys stamped with the moment they arrive from the window, and put on the
+// media timeline only when the gameplay is next updated. Their timing then
+// does not depend on how often the frontend updates.
+use std::time::Instant;
+
+use crate::clock::GameClock;
+use crate::replay::{Input, KeyAction};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TimedKey {
+    pub instant: Instant,
+    pub key: char,
+    pub action: KeyAction,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct InputQueue {
+    keys: Vec<TimedKey>,
+}
+
+impl InputQueue {
+    pub fn new() -> InputQueue {
+        InputQueue::default()
+    }
+
+    pub fn push(&mut self, instant: Instant, key: char, action: KeyAction) {
+        self.keys.push(TimedKey { instant, key, action });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Every key queued at its media time on `clock`, in the order they
+    /// arrived, emptying the queue.
+    pub fn drain<C: GameClock + ?Sized>(&mut self, clock: &C) -> Vec<Input> {
+        self.drain_timed(clock).into_iter().map(|(_, input)| input).collect()
+    }
+
+    /// Like `drain`, with the moment each key arrived.
+    pub fn drain_timed<C: GameClock + ?Sized>(&mut self, clock: &C) -> Vec<(Instant, Input)> {
+        self.keys
+            .drain(..)
+            .map(|key| {
+                let input = Input {
+                    time: clock.time_at(key.instant),
+                    key: key.key,
+                    action: key.action,
+                };
+                (key.instant, input)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,83 @@
+// How close to the target time a key press was, and how much that is worth.
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Judgement {
+    Perfect,
+    Great,
+    Good,
+    Miss,
+}
+
+impl Judgement {
+    pub const ALL: [Judgement; 4] = [
+        Judgement::Perfect,
+        Judgement::Great,
+        Judgement::Good,
+        Judgement::Miss,
+    ];
+
+    /// Fraction of the full note value the judgement is worth.
+    pub fn weight(self) -> f64 {
+        match self {
+            Judgement::Perfect => 1.0,
+            Judgement::Great => 0.7,
+            Judgement::Good => 0.4,
+            Judgement::Miss => 0.0,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Judgement::Perfect => "Perfect",
+            Judgement::Great => "Great",
+            Judgement::Good => "Good",
+            Judgement::Miss => "Miss",
+        }
+    }
+}
+
+/// Half-widths of the timing windows, in seconds. A press `offset` seconds
+/// away from its target gets the first judgement whose window contains it.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Windows {
+    pub perfect: f64,
+    pub great: f64,
+    pub good: f64,
+}
+
+impl Default for Windows {
+    fn default() -> Windows {
+        Windows {
+            perfect: 0.040,
+            great: 0.080,
+            good: 0.130,
+        }
+    }
+}
+
+impl Windows {
+    /// The windows in media seconds when the media plays at `rate` times the
+    /// normal speed, as wide as these in real seconds.
+    pub fn scaled(&self, rate: f64) -> Windows {
+        Windows {
+            perfect: self.perfect * rate,
+            great: self.great * rate,
+            good: self.good * rate,
+        }
+    }
+
+    /// Judge a press `offset` seconds away from its target (negative is
+    /// early). `None` means the press is too far away to belong to the note.
+    pub fn judge(&self, offset: f64) -> Option<Judgement> {
+        let distance = offset.abs();
+        if distance <= self.perfect {
+            Some(Judgement::Perfect)
+        } else if distance <= self.great {
+            Some(Judgement::Great)
+        } else if distance <= self.good {
+            Some(Judgement::Good)
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,28 @@
+// The game without its frontend: charts and their converters, judgement,
+// typing, scoring, replays, high scores, player profiles, calibration,
+// practice rates, the highway layout, frame statistics, the game clock, the
+// schedule of the event loop and the exit codes of the command line.
+// Nothing here opens a window or plays media, so it can be tested and
+// scripted on its own; the `mechanical` binary is the player.
+pub mod calibration;
+pub mod chart;
+pub mod clock;
+pub mod convert;
+pub mod exit;
+pub mod gameplay;
+pub mod ghost;
+pub mod highway;
+pub mod input;
+pub mod json;
+pub mod judgement;
+pub mod lint;
+pub mod lyrics;
+pub mod perf;
+pub mod practice;
+pub mod profile;
+pub mod replay;
+pub mod results;
+pub mod schedule;
+pub mod score;
+pub mod scores;
+pub mod typing;
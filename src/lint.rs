@@ -0,0 +1,277 @@
+// Checks run on a chart file before publishing it. Unlike `Chart::parse`,
+// linting does not stop at the first problem and keeps the line of every
+// note to point at it.
+use std::fmt;
+
+use crate::chart::{Chart, Note, NoteKind, PhraseTiming};
+use crate::json;
+
+/// More presses than this in one second can't be played.
+pub const MAX_PRESSES_PER_SECOND: usize = 20;
+/// More keys than this pressed at once can't be played with two hands.
+pub const MAX_CHORD: usize = 6;
+/// Notes of the same key closer than this are taken as overlapping.
+const SAME_TIME: f64 = 0.001;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Line of the chart file, when the problem is on one.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}: {}: {}", line, self.severity.name(), self.message),
+            None => write!(f, "{}: {}", self.severity.name(), self.message),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Options {
+    /// Duration of the media, notes after it are errors.
+    pub media_duration: Option<f64>,
+    /// Characters of the keyboard layout the chart is played on. Every
+    /// printable ASCII character when `None`.
+    pub layout: Option<Vec<char>>,
+}
+
+#[derive(Default)]
+struct Linter {
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Linter {
+    fn error(&mut self, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Error, line, message });
+    }
+
+    fn warning(&mut self, line: Option<usize>, message: String) {
+        self.diagnostics.push(Diagnostic { severity: Severity::Warning, line, message });
+    }
+}
+
+fn is_supported(character: char, layout: &Option<Vec<char>>) -> bool {
+    match layout {
+        Some(layout) => layout.contains(&character),
+        None => character == ' ' || character.is_ascii_graphic(),
+    }
+}
+
+// Every key press of a note with its time, for the density checks
+fn presses(note: &Note) -> Vec<(f64, char)> {
+    match note.kind {
+        NoteKind::Key(key) | NoteKind::Hold { key, .. } => vec![(note.time, key)],
+        NoteKind::Phrase(ref phrase) => match phrase.timing {
+            PhraseTiming::PerCharacter(ref times) => {
+                times.iter().cloned().zip(phrase.text.chars()).collect()
+            }
+            // Spread evenly over the window
+            PhraseTiming::Window { end } => {
+                let length = phrase.len().max(1) as f64;
+                phrase
+                    .text
+                    .chars()
+                    .enumerate()
+                    .map(|(index, key)| (note.time + (end - note.time) * index as f64 / length, key))
+                    .collect()
+            }
+        },
+    }
+}
+
+/// Lint the contents of a chart file, diagnostics are sorted by line.
+pub fn lint(contents: &str, options: &Options) -> Vec<Diagnostic> {
+    let mut linter = Linter::default();
+    let mut chart = Chart::default();
+    // Line of each note of `chart.notes`
+    let mut note_lines = Vec::new();
+    let mut section = String::new();
+    let mut metadata_lines = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let number = number + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if line.starts_with('[') && line.ends_with(']') {
+            section = line[1..line.len() - 1].to_string();
+            continue;
+        }
+        if section == "Metadata" {
+            if !line.contains('=') {
+                linter.error(Some(number), format!("metadata line without '=': {}", line));
+                continue;
+            }
+            metadata_lines.push(number);
+        }
+        let notes = chart.notes.len();
+        if let Err(err) = chart.parse_line(&section, line) {
+            linter.error(Some(number), format!("{:#}", err));
+        }
+        if chart.notes.len() > notes {
+            note_lines.push(number);
+        }
+    }
+
+    lint_metadata(&mut linter, &chart, metadata_lines.first().cloned());
+    lint_notes(&mut linter, &chart, &note_lines, options);
+
+    linter.diagnostics.sort_by_key(|diagnostic| diagnostic.line.unwrap_or(0));
+    linter.diagnostics
+}
+
+fn lint_metadata(linter: &mut Linter, chart: &Chart, line: Option<usize>) {
+    if chart.metadata.title.is_empty() {
+        linter.error(line, "the chart has no title".to_string());
+    }
+    if chart.metadata.audio.is_empty() {
+        linter.error(line, "the chart has no audio file".to_string());
+    }
+    if chart.metadata.artist.is_empty() {
+        linter.warning(line, "the chart has no artist".to_string());
+    }
+    if chart.metadata.creator.is_empty() {
+        linter.warning(line, "the chart has no creator".to_string());
+    }
+    if chart.notes.is_empty() {
+        linter.error(None, "the chart has no notes".to_string());
+    }
+}
+
+fn lint_notes(linter: &mut Linter, chart: &Chart, note_lines: &[usize], options: &Options) {
+    // End time and line of the last note, per key
+    let mut last_of_key: Vec<(char, f64, usize)> = Vec::new();
+    let mut all_presses = Vec::new();
+    let mut previous_time = None;
+
+    for (note, number) in chart.notes.iter().zip(note_lines.iter().cloned()) {
+        let line = Some(number);
+        if let Some(previous) = previous_time {
+            if note.time < previous {
+                linter.error(line, format!("note at {:.3} comes after a note at {:.3}", note.time, previous));
+            }
+        }
+        previous_time = Some(note.time);
+
+        if note.time < 0.0 {
+            linter.error(line, format!("note at {:.3} is before the start of the media", note.time));
+        }
+        if note.end_time() < note.time {
+            linter.error(line, format!("note ends at {:.3}, before it starts", note.end_time()));
+        }
+        if let Some(duration) = options.media_duration {
+            if note.end_time() > duration {
+                linter.error(line, format!("note ends at {:.3}, after the end of the media at {:.3}",
+                    note.end_time(), duration));
+            }
+        }
+
+        match note.kind {
+            NoteKind::Key(key) | NoteKind::Hold { key, .. } => {
+                match last_of_key.iter_mut().find(|(k, _, _)| *k == key) {
+                    Some(last) => {
+                        if note.time <= last.1 + SAME_TIME {
+                            linter.error(line, format!("'{}' at {:.3} overlaps the note on line {}",
+                                key, note.time, last.2));
+                        }
+                        *last = (key, note.end_time(), number);
+                    }
+                    None => last_of_key.push((key, note.end_time(), number)),
+                }
+            }
+            NoteKind::Phrase(ref phrase) => {
+                if phrase.is_empty() {
+                    linter.error(line, "phrase without text".to_string());
+                }
+                if let PhraseTiming::PerCharacter(ref times) = phrase.timing {
+                    if times.len() != phrase.len() {
+                        linter.error(line, format!("{} times for the {} characters of \"{}\"",
+                            times.len(), phrase.len(), phrase.text));
+                    }
+                    if times.windows(2).any(|pair| pair[1] < pair[0]) {
+                        linter.error(line, format!("character times of \"{}\" are not sorted", phrase.text));
+                    }
+                }
+            }
+        }
+
+        let mut unsupported: Vec<char> = Vec::new();
+        for (time, key) in presses(note) {
+            if !is_supported(key, &options.layout) && !unsupported.contains(&key) {
+                unsupported.push(key);
+            }
+            all_presses.push((time, number));
+        }
+        for key in unsupported {
+            linter.error(line, format!("'{}' can't be typed with the selected layout", key));
+        }
+    }
+
+    lint_density(linter, all_presses);
+}
+
+fn lint_density(linter: &mut Linter, mut presses: Vec<(f64, usize)>) {
+    presses.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    // Report each dense part once, on the line where it starts
+    let mut reported_until = f64::MIN;
+    let mut start = 0;
+    for end in 0..presses.len() {
+        while presses[end].0 - presses[start].0 >= 1.0 {
+            start += 1;
+        }
+        if end - start + 1 > MAX_PRESSES_PER_SECOND && presses[start].0 > reported_until {
+            linter.warning(Some(presses[start].1), format!(
+                "more than {} presses in one second from {:.3}", MAX_PRESSES_PER_SECOND, presses[start].0));
+            reported_until = presses[start].0 + 1.0;
+        }
+    }
+
+    let mut index = 0;
+    while index < presses.len() {
+        let chord = presses[index..]
+            .iter()
+            .take_while(|(time, _)| time - presses[index].0 < SAME_TIME)
+            .count();
+        if chord > MAX_CHORD {
+            linter.warning(Some(presses[index].1), format!(
+                "{} keys pressed at once at {:.3}, at most {} can be played", chord, presses[index].0, MAX_CHORD));
+        }
+        index += chord;
+    }
+}
+
+/// Diagnostics as a JSON array of `{"line", "severity", "message"}` objects.
+pub fn to_json(diagnostics: &[Diagnostic]) -> String {
+    let objects: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            format!(
+                "{{\"line\":{},\"severity\":\"{}\",\"message\":{}}}",
+                json::number(diagnostic.line),
+                diagnostic.severity.name(),
+                json::string(&diagnostic.message)
+            )
+        })
+        .collect();
+    format!("[{}]", objects.join(","))
+}
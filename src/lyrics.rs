@@ -0,0 +1,362 @@
+// Synced lyrics from .lrc (plain and enhanced, with per word timestamps) and
+// .srt files, turned into typing charts where every line or word is a phrase
+// note.
+use std::fs;
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+
+use crate::chart::{Chart, Metadata, Note, Phrase};
+
+/// How long the last line lasts when the file does not say, in seconds.
+pub const DEFAULT_LINE_LENGTH: f64 = 5.0;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Word {
+    pub time: f64,
+    pub text: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Line {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    /// Per word timestamps, only enhanced LRC files have them.
+    pub words: Vec<Word>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Lyrics {
+    pub metadata: Metadata,
+    /// Lines sorted by start time.
+    pub lines: Vec<Line>,
+}
+
+impl Lyrics {
+    pub fn load(path: &Path) -> Result<Lyrics> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(|extension| extension.to_lowercase());
+        match extension.as_deref() {
+            Some("lrc") => parse_lrc(&contents),
+            Some("srt") => parse_srt(&contents),
+            _ => bail!("{} is not a .lrc or .srt file", path.display()),
+        }
+    }
+
+    /// Add a line keeping them sorted, lines already there are skipped.
+    pub fn add_line(&mut self, line: Line) {
+        if self.lines.contains(&line) {
+            return;
+        }
+        let index = self.lines
+            .iter()
+            .position(|other| other.start > line.start)
+            .unwrap_or(self.lines.len());
+        self.lines.insert(index, line);
+    }
+
+    /// The line being sung at `time`, and the one after it.
+    pub fn lines_at(&self, time: f64) -> (Option<&Line>, Option<&Line>) {
+        let next_index = self.lines
+            .iter()
+            .position(|line| line.start > time)
+            .unwrap_or(self.lines.len());
+        let current = next_index
+            .checked_sub(1)
+            .map(|index| &self.lines[index])
+            .filter(|line| time <= line.end);
+        (current, self.lines.get(next_index))
+    }
+
+    /// Turn every line into a phrase note. With `per_word`, lines that have
+    /// word timestamps are split into one phrase note per word instead.
+    pub fn to_chart(&self, per_word: bool) -> Chart {
+        let mut chart = Chart::new(self.metadata.clone());
+        for line in &self.lines {
+            if per_word && !line.words.is_empty() {
+                for (index, word) in line.words.iter().enumerate() {
+                    let end = line.words
+                        .get(index + 1)
+                        .map(|next| next.time)
+                        .unwrap_or(line.end);
+                    let text = word.text.trim();
+                    if !text.is_empty() {
+                        chart.add_note(Note::phrase(word.time, Phrase::window(text, end)));
+                    }
+                }
+            } else {
+                chart.add_note(Note::phrase(line.start, Phrase::window(&line.text, line.end)));
+            }
+        }
+        chart
+    }
+}
+
+impl Line {
+    /// How many characters of the line have been sung at `time`, for the
+    /// karaoke highlight. Words fill up progressively until the next word
+    /// starts, lines without word timestamps fill up evenly.
+    pub fn sung_characters(&self, time: f64) -> usize {
+        let length = self.text.chars().count();
+        if time <= self.start {
+            return 0;
+        }
+        if time >= self.end || self.end <= self.start {
+            return length;
+        }
+        if self.words.is_empty() {
+            let fraction = (time - self.start) / (self.end - self.start);
+            return (fraction * length as f64) as usize;
+        }
+
+        let mut sung = 0;
+        let mut search_from = 0;
+        for (index, word) in self.words.iter().enumerate() {
+            if word.time > time {
+                break;
+            }
+            let word_end = self.words
+                .get(index + 1)
+                .map(|next| next.time)
+                .unwrap_or(self.end);
+            let word_start = self.text[search_from..]
+                .find(&word.text)
+                .map(|found| found + search_from)
+                .unwrap_or(search_from);
+            let fraction = if word_end > word.time {
+                ((time - word.time) / (word_end - word.time)).min(1.0)
+            } else {
+                1.0
+            };
+            let word_length = word.text.chars().count();
+            sung = self.text[..word_start].chars().count()
+                + (fraction * word_length as f64).round() as usize;
+            search_from = (word_start + word.text.len()).min(self.text.len());
+        }
+        sung.min(length)
+    }
+}
+
+// LRC Section
+// "mm:ss.xx" to seconds
+fn parse_lrc_timestamp(timestamp: &str) -> Option<f64> {
+    let mut parts = timestamp.trim().splitn(2, ':');
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    // "nan" and "inf" parse as numbers
+    if !minutes.is_finite() || !seconds.is_finite() || minutes < 0.0 || seconds < 0.0 {
+        return None;
+    }
+    Some(minutes * 60.0 + seconds)
+}
+
+// Split the leading "[...]" tags from the rest of a line
+fn split_lrc_tags(line: &str) -> (Vec<&str>, &str) {
+    let mut tags = Vec::new();
+    let mut rest = line.trim();
+    while rest.starts_with('[') {
+        match rest.find(']') {
+            Some(close) => {
+                tags.push(&rest[1..close]);
+                rest = rest[close + 1..].trim_start();
+            }
+            None => break,
+        }
+    }
+    (tags, rest)
+}
+
+// "<mm:ss.xx>word <mm:ss.xx>word" to words, text before the first
+// timestamp belongs to the line time. A '<' that does not open a timestamp
+// is part of the lyrics.
+fn parse_lrc_words(text: &str, line_time: f64) -> (String, Vec<Word>) {
+    // Text after each timestamp, the first one at the line time
+    let mut segments = vec![(line_time, String::new())];
+    let mut rest = text;
+    while let Some(open) = rest.find('<') {
+        let tag = rest[open + 1..].find('>').map(|close| &rest[open + 1..open + 1 + close]);
+        let timestamp = tag.and_then(|tag| parse_lrc_timestamp(tag).map(|time| (time, tag.len())));
+        let segment = &mut segments.last_mut().unwrap().1;
+        match timestamp {
+            Some((time, length)) => {
+                segment.push_str(&rest[..open]);
+                rest = &rest[open + length + 2..];
+                segments.push((time, String::new()));
+            }
+            None => {
+                segment.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    segments.last_mut().unwrap().1.push_str(rest);
+    if segments.len() == 1 {
+        return (text.trim().to_string(), Vec::new());
+    }
+    let words = segments
+        .iter()
+        .filter(|(_, word)| !word.trim().is_empty())
+        .map(|(time, word)| Word {
+            time: *time,
+            text: word.trim().to_string(),
+        })
+        .collect();
+    let plain: String = segments.iter().map(|(_, word)| word.as_str()).collect();
+    (plain.split_whitespace().collect::<Vec<_>>().join(" "), words)
+}
+
+pub fn parse_lrc(contents: &str) -> Result<Lyrics> {
+    let mut lyrics = Lyrics::default();
+    let mut offset = 0.0;
+    // Start time and text, a line ends when the next one starts
+    let mut timed: Vec<(f64, String, Vec<Word>)> = Vec::new();
+
+    for (number, line) in contents.lines().enumerate() {
+        let (tags, text) = split_lrc_tags(line);
+        let mut times = Vec::new();
+        for tag in tags {
+            if let Some(time) = parse_lrc_timestamp(tag) {
+                times.push(time);
+                continue;
+            }
+            let mut parts = tag.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim().to_lowercase();
+            let value = parts.next().unwrap_or("").trim();
+            match key.as_str() {
+                "ti" => lyrics.metadata.title = value.to_string(),
+                "ar" => lyrics.metadata.artist = value.to_string(),
+                "by" => lyrics.metadata.creator = value.to_string(),
+                // Milliseconds, a positive offset shows the lyrics sooner
+                "offset" => {
+                    let milliseconds: f64 = value
+                        .trim_start_matches('+')
+                        .parse()
+                        .with_context(|| format!("line {}: invalid offset {}", number + 1, value))?;
+                    if !milliseconds.is_finite() {
+                        bail!("line {}: invalid offset {}", number + 1, value);
+                    }
+                    offset = milliseconds / 1000.0;
+                }
+                _ => {}
+            }
+        }
+        for time in times {
+            let (text, words) = parse_lrc_words(text, time);
+            timed.push((time, text, words));
+        }
+    }
+
+    timed.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    for (index, (start, text, words)) in timed.iter().enumerate() {
+        // Empty lines only mark the end of the previous one
+        if text.is_empty() {
+            continue;
+        }
+        let end = timed
+            .get(index + 1)
+            .map(|next| next.0)
+            .unwrap_or(start + DEFAULT_LINE_LENGTH);
+        lyrics.lines.push(Line {
+            start: (start - offset).max(0.0),
+            end: (end - offset).max(0.0),
+            text: text.clone(),
+            words: words
+                .iter()
+                .map(|word| Word {
+                    time: (word.time - offset).max(0.0),
+                    text: word.text.clone(),
+                })
+                .collect(),
+        });
+    }
+    Ok(lyrics)
+}
+
+// SRT Section
+// "hh:mm:ss,mmm" to seconds
+fn parse_srt_timestamp(timestamp: &str) -> Option<f64> {
+    let timestamp = timestamp.trim().replace(',', ".");
+    let mut parts = timestamp.splitn(3, ':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    // "nan" and "inf" parse as numbers
+    if !hours.is_finite() || !minutes.is_finite() || !seconds.is_finite() {
+        return None;
+    }
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+// Subtitles may carry <i>, <b> or {\an8} style markup
+pub fn strip_markup(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut closing = None;
+    for character in text.chars() {
+        match (closing, character) {
+            (None, '<') => closing = Some('>'),
+            (None, '{') => closing = Some('}'),
+            (Some(close), character) if character == close => closing = None,
+            (Some(_), _) => {}
+            (None, character) => plain.push(character),
+        }
+    }
+    plain
+}
+
+pub fn parse_srt(contents: &str) -> Result<Lyrics> {
+    let mut lyrics = Lyrics::default();
+    let mut lines = contents.lines().enumerate().peekable();
+
+    while let Some((number, line)) = lines.next() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        if line.is_empty() {
+            continue;
+        }
+        // The cue number is optional in the wild, go straight for the timing
+        let timing = if line.contains("-->") {
+            line.to_string()
+        } else {
+            match lines.next() {
+                Some((_, timing)) => timing.trim().to_string(),
+                None => bail!("line {}: cue without timing", number + 1),
+            }
+        };
+        let mut parts = timing.splitn(2, "-->");
+        let start = parts.next().and_then(parse_srt_timestamp);
+        // Ignore positioning after the end timestamp
+        let end = parts
+            .next()
+            .and_then(|end| end.split_whitespace().next())
+            .and_then(parse_srt_timestamp);
+        let (start, end) = match (start, end) {
+            (Some(start), Some(end)) => (start, end),
+            _ => bail!("line {}: invalid cue timing {}", number + 2, timing),
+        };
+
+        let mut text = Vec::new();
+        while let Some((_, line)) = lines.peek() {
+            if line.trim().is_empty() {
+                break;
+            }
+            text.push(strip_markup(line.trim()));
+            lines.next();
+        }
+        let text = text.join(" ").trim().to_string();
+        if !text.is_empty() {
+            lyrics.lines.push(Line {
+                start,
+                end,
+                text,
+                words: Vec::new(),
+            });
+        }
+    }
+
+    lyrics.lines.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap());
+    Ok(lyrics)
+}
@@ -13,27 +13,96 @@ use std::sync::{Arc, Mutex};
 // other imports
 //extern crate raw_window_handle;
 
+use mechanical::{gameplay, lyrics};
+
+mod benchmark;
+mod calibrate;
+mod cli;
+mod highway_renderer;
+mod media_info;
 mod media_player;
+mod pipeline_clock;
+mod subtitles;
 mod support;
 
 fn main() {
-    //let mut application_state: Arc<Mutex<AppWindow>> = Arc::new(Mutex::new(AppWindow::new()));
-    let mut application_state: AppWindow = AppWindow::new();
-    media_player::media_player::main(application_state);
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(cli::run(&args));
 }
 
         
-#[derive(Copy, Clone)]
 pub struct AppWindow {
     app_font_id: Option<conrod_core::text::font::Id>,
     slider_indicator_loop_set: bool,
+    // Gameplay
+    gameplay: gameplay::Gameplay,
+    // Scan code of the last key pressed and when, and the character of
+    // every key held down, to tell the gameplay when it is released
+    pressed_key: Option<(u32, std::time::Instant)>,
+    held_keys: Vec<(u32, char)>,
+    // Keys not played into the gameplay yet
+    input_queue: mechanical::input::InputQueue,
+    // The media played to its end, the results are shown
+    finished: bool,
+    // Where the replay of the play was saved
+    replay_path: Option<std::path::PathBuf>,
+    // Leave the player at the next event
+    quit: bool,
+    // Who is playing, picked at startup unless given on the command line,
+    // and the high scores of every play
+    profile: mechanical::profile::Profile,
+    profiles: Option<mechanical::profile::Profiles>,
+    picking_profile: bool,
+    new_profile_name: String,
+    scores: Option<mechanical::scores::ScoreDatabase>,
+    // Why plays are not recorded, shown with the results
+    scores_error: Option<String>,
+    // Replay played instead of the keyboard
+    playback: Option<mechanical::replay::Playback>,
+    // Replay raced against
+    ghost: Option<mechanical::ghost::Ghost>,
+    // Build the chart from the subtitles of the media
+    use_subtitle_chart: bool,
+    // Lines shown over the video, from the subtitles or a lyrics file
+    lyrics: lyrics::Lyrics,
+    // Where the chart is saved when editing
+    editing: Option<std::path::PathBuf>,
+    // Tick and frame rates while playing
+    loop_config: support::LoopConfig,
+    // Rate to play the media at once it plays, seeking before fails
+    pending_rate: Option<f64>,
+    // Frame times and latencies, shown over the game when asked for
+    perf: mechanical::perf::FrameStats,
+    show_perf: bool,
 }
 
 impl AppWindow {
     fn new () -> AppWindow {
         AppWindow {
             app_font_id: None,
-            slider_indicator_loop_set: false
+            slider_indicator_loop_set: false,
+            gameplay: gameplay::Gameplay::default(),
+            pressed_key: None,
+            held_keys: Vec::new(),
+            input_queue: mechanical::input::InputQueue::new(),
+            finished: false,
+            replay_path: None,
+            quit: false,
+            profile: mechanical::profile::Profile::new(""),
+            profiles: None,
+            picking_profile: true,
+            new_profile_name: String::new(),
+            scores: None,
+            scores_error: None,
+            playback: None,
+            ghost: None,
+            use_subtitle_chart: true,
+            lyrics: lyrics::Lyrics::default(),
+            editing: None,
+            loop_config: support::LoopConfig::default(),
+            pending_rate: None,
+            perf: mechanical::perf::FrameStats::new(),
+            show_perf: false,
         }
     }
 }
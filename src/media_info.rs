@@ -0,0 +1,188 @@
+// What GStreamer can tell about a media file without playing it.
+use std::fmt;
+use std::path::Path;
+
+use anyhow::{anyhow, Context, Result};
+use gstreamer_pbutils::prelude::*;
+
+use mechanical::json;
+
+/// Longest time given to GStreamer to look into a file.
+const DISCOVER_TIMEOUT_SECONDS: u64 = 10;
+
+/// URIs are passed through, paths are made absolute and turned into file URIs.
+pub fn to_uri(media: &str) -> Result<String> {
+    if media.contains("://") {
+        return Ok(media.to_string());
+    }
+    let path = Path::new(media);
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    Ok(glib::filename_to_uri(&path, None)
+        .with_context(|| format!("Could not make an URI of {}", path.display()))?
+        .to_string())
+}
+
+fn discover(media: &str) -> Result<gstreamer_pbutils::DiscovererInfo> {
+    gstreamer::init()?;
+    let uri = to_uri(media)?;
+    let discoverer = gstreamer_pbutils::Discoverer::new(
+        gstreamer::ClockTime::from_seconds(DISCOVER_TIMEOUT_SECONDS))?;
+    discoverer
+        .discover_uri(&uri)
+        .with_context(|| format!("Could not read {}", media))
+}
+
+/// Duration of the media, in seconds.
+pub fn duration(media: &str) -> Result<f64> {
+    discover(media)?
+        .get_duration()
+        .nseconds()
+        .map(|nanoseconds| nanoseconds as f64 / 1_000_000_000.0)
+        .ok_or_else(|| anyhow!("{} has no known duration", media))
+}
+
+/// The streams of a media, what `analize_streams` prints while playing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MediaInfo {
+    pub uri: String,
+    /// Seconds.
+    pub duration: Option<f64>,
+    pub seekable: bool,
+    pub streams: Vec<StreamInfo>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StreamInfo {
+    /// "video", "audio" or "subtitles".
+    pub kind: String,
+    pub codec: Option<String>,
+    pub language: Option<String>,
+    pub bitrate: Option<u32>,
+    /// Video size in pixels.
+    pub size: Option<(u32, u32)>,
+    pub channels: Option<u32>,
+    pub sample_rate: Option<u32>,
+}
+
+fn stream_info(kind: &str, stream: &gstreamer_pbutils::DiscovererStreamInfo) -> StreamInfo {
+    let mut info = StreamInfo {
+        kind: kind.to_string(),
+        ..StreamInfo::default()
+    };
+    if let Some(tags) = stream.get_tags() {
+        info.codec = tags
+            .get::<gstreamer::tags::VideoCodec>()
+            .and_then(|codec| codec.get().map(|codec| codec.to_string()))
+            .or_else(|| tags
+                .get::<gstreamer::tags::AudioCodec>()
+                .and_then(|codec| codec.get().map(|codec| codec.to_string())))
+            .or_else(|| tags
+                .get::<gstreamer::tags::SubtitleCodec>()
+                .and_then(|codec| codec.get().map(|codec| codec.to_string())));
+        info.language = tags
+            .get::<gstreamer::tags::LanguageCode>()
+            .and_then(|language| language.get().map(|language| language.to_string()));
+        info.bitrate = tags
+            .get::<gstreamer::tags::Bitrate>()
+            .and_then(|bitrate| bitrate.get());
+    }
+    if let Some(video) = stream.downcast_ref::<gstreamer_pbutils::DiscovererVideoInfo>() {
+        info.size = Some((video.get_width(), video.get_height()));
+    }
+    if let Some(audio) = stream.downcast_ref::<gstreamer_pbutils::DiscovererAudioInfo>() {
+        info.channels = Some(audio.get_channels());
+        info.sample_rate = Some(audio.get_sample_rate());
+        if info.language.is_none() {
+            info.language = audio.get_language().map(|language| language.to_string());
+        }
+    }
+    info
+}
+
+pub fn info(media: &str) -> Result<MediaInfo> {
+    let discovered = discover(media)?;
+    let mut info = MediaInfo {
+        uri: to_uri(media)?,
+        duration: discovered
+            .get_duration()
+            .nseconds()
+            .map(|nanoseconds| nanoseconds as f64 / 1_000_000_000.0),
+        seekable: discovered.get_seekable(),
+        streams: Vec::new(),
+    };
+    for stream in discovered.get_video_streams() {
+        info.streams.push(stream_info("video", &stream));
+    }
+    for stream in discovered.get_audio_streams() {
+        info.streams.push(stream_info("audio", &stream));
+    }
+    for stream in discovered.get_subtitle_streams() {
+        info.streams.push(stream_info("subtitles", &stream));
+    }
+    Ok(info)
+}
+
+impl MediaInfo {
+    pub fn to_json(&self) -> String {
+        let streams: Vec<String> = self.streams
+            .iter()
+            .map(|stream| {
+                format!(
+                    "{{\"kind\":{},\"codec\":{},\"language\":{},\"bitrate\":{},\"width\":{},\"height\":{},\"channels\":{},\"sample_rate\":{}}}",
+                    json::string(&stream.kind),
+                    stream.codec.as_ref().map(|codec| json::string(codec)).unwrap_or_else(|| "null".to_string()),
+                    stream.language.as_ref().map(|language| json::string(language)).unwrap_or_else(|| "null".to_string()),
+                    json::number(stream.bitrate),
+                    json::number(stream.size.map(|size| size.0)),
+                    json::number(stream.size.map(|size| size.1)),
+                    json::number(stream.channels),
+                    json::number(stream.sample_rate)
+                )
+            })
+            .collect();
+        format!(
+            "{{\"uri\":{},\"duration\":{},\"seekable\":{},\"streams\":[{}]}}",
+            json::string(&self.uri),
+            json::number(self.duration),
+            self.seekable,
+            streams.join(",")
+        )
+    }
+}
+
+impl fmt::Display for MediaInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "uri: {}", self.uri)?;
+        match self.duration {
+            Some(duration) => writeln!(f, "duration: {:.3} s", duration)?,
+            None => writeln!(f, "duration: unknown")?,
+        }
+        writeln!(f, "seekable: {}", self.seekable)?;
+        for (index, stream) in self.streams.iter().enumerate() {
+            writeln!(f, "{} stream {}:", stream.kind, index)?;
+            if let Some(ref codec) = stream.codec {
+                writeln!(f, "  codec: {}", codec)?;
+            }
+            if let Some(ref language) = stream.language {
+                writeln!(f, "  language: {}", language)?;
+            }
+            if let Some(bitrate) = stream.bitrate {
+                writeln!(f, "  bitrate: {}", bitrate)?;
+            }
+            if let Some((width, height)) = stream.size {
+                writeln!(f, "  size: {}x{}", width, height)?;
+            }
+            if let Some(channels) = stream.channels {
+                writeln!(f, "  channels: {}", channels)?;
+            }
+            if let Some(sample_rate) = stream.sample_rate {
+                writeln!(f, "  sample rate: {}", sample_rate)?;
+            }
+        }
+        Ok(())
+    }
+}
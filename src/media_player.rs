@@ -15,8 +15,26 @@ pub mod media_player {
     use crate::support;
     // mechanical
     use crate::AppWindow;
+    use mechanical::chart::{Chart, Note, NoteKind};
+    use mechanical::clock::{GameClock, OffsetClock};
+    use mechanical::gameplay::Gameplay;
+    use mechanical::highway::{self, JUDGEMENT_LINE_MARGIN, JUDGEMENT_SHOWN};
+    use mechanical::judgement::Judgement;
+    use mechanical::perf;
+    use mechanical::replay::KeyAction;
+    use mechanical::profile::{self, Profiles, Theme};
+    use mechanical::replay;
+    use mechanical::results::Results;
+    use mechanical::scores::{Entry, ScoreDatabase};
+    use crate::highway_renderer::HighwayRenderer;
+    use crate::pipeline_clock::PipelineClock;
+    use crate::subtitles::{self, SubtitleCapture};
     // sync
     use std::sync::{Arc, Mutex};
+    use std::cmp::Ordering;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{Instant, SystemTime, UNIX_EPOCH};
     // other imports
     use raw_window_handle;
     use raw_window_handle::{HasRawWindowHandle};
@@ -27,7 +45,30 @@ pub mod media_player {
         common: widget::CommonBuilder,
     }
     
-    pub fn main(mut application_state: AppWindow) {
+    const REPLAY_DIRECTORY: &str = "replays";
+    /// Every play finished, in the working directory.
+    pub const SCORES_FILE: &str = "scores.mrscores";
+    /// Profiles of the players, in the working directory.
+    pub const PROFILES_FILE: &str = "profiles.mrprofiles";
+    // Replay playback speed
+    const MIN_PLAYBACK_SPEED: f64 = 0.25;
+    const MAX_PLAYBACK_SPEED: f64 = 2.0;
+    // Results
+    const HISTOGRAM_BINS: usize = 25;
+    const RESULT_SECTIONS: usize = 40;
+    const LEADERBOARD_SIZE: usize = 5;
+    // Performance overlay, in pixels, and the frame time at the top of its
+    // graph in seconds
+    const PERF_WIDTH: f64 = 260.0;
+    const PERF_HEIGHT: f64 = 190.0;
+    const PERF_GRAPH_HEIGHT: f64 = 50.0;
+    const PERF_GRAPH_TOP: f64 = 2.0 / 60.0;
+
+    /// Played when no media is given.
+    pub const DEMO_URI: &str = "https://www.freedesktop.org/software/gstreamer-sdk/\
+                                data/media/sintel_trailer-480p.webm";
+
+    pub fn main(mut application_state: AppWindow, uri: &str) {
         //let application_state = Arc::clone(&application_state);
         //let mut application_state_lock = application_state.lock().unwrap();
         const WIDTH: u32 = 800;
@@ -42,13 +83,18 @@ pub mod media_player {
         let window = glium::glutin::window::WindowBuilder::new()
             .with_title(APPLICATION_NAME)
             .with_inner_size(glium::glutin::dpi::LogicalSize::new(WIDTH, HEIGHT));
+        // Waiting for the screen would cap the frame rate
+        let loop_config = application_state.loop_config;
         let context = glutin::ContextBuilder::new()
-            .with_vsync(true)
+            .with_vsync(loop_config.max_fps.is_some())
             .with_multisampling(4);
         let display = glium::Display::new(window
             , context, &event_loop).unwrap();
         // Hook the video streamer to the window
-        start_gstreamer(&display);
+        // Practice and replays played back faster or slower change the speed
+        let preserve_pitch = application_state.pending_rate.is_some() || application_state.playback.is_some();
+        let (playbin, subtitle_capture) = start_gstreamer(&display, uri, preserve_pitch);
+        let clock = PipelineClock::new(&playbin);
         // Construct the UI
         let mut ui = conrod_core::UiBuilder::new([WIDTH as f64
             , HEIGHT as f64]).build();
@@ -60,11 +106,23 @@ pub mod media_player {
         let font_path = assets.join(path_to_font);
         let app_font_id = ui.fonts.insert_from_file(font_path).unwrap();
         application_state.app_font_id = Some(app_font_id);
+        open_scores(&mut application_state);
+        application_state.profiles = open_profiles();
+        if application_state.picking_profile {
+            // Waits for the player to pick a profile
+            pause_button_logic(&playbin);
+        } else {
+            let name = application_state.profile.name.clone();
+            select_profile(&mut application_state, &name);
+        }
 
         // A type used for converting 'conrod_core::render::Primitives' into 'Command'
         // that can be used for drawing to the glium 'Surface'
         let mut renderer =
             conrod_glium::Renderer::new(&display).unwrap();
+        // The notes, drawn over the UI in one pass
+        let mut highway_renderer =
+            HighwayRenderer::new(&display, ui.fonts.get(app_font_id).unwrap()).unwrap();
 
         // The image map describing each of our widget->image mappings (in our case, none)
         let image_map =
@@ -73,15 +131,24 @@ pub mod media_player {
 
         // Instantiate the generated list of widget identifiers
         let mut ids = Ids::new(ui.widget_id_generator());
+        // Frames are timed only while animating, the wait for events between
+        // the others is not drawing time
+        let mut animated = false;
+        let mut last_frame: Option<Instant> = None;
 
         // Poll events from the window.
-        support::run_loop(display, event_loop, move |request, display| {
+        support::run_loop(display, event_loop, loop_config, move |request, display| {
             match request {
                 support::Request::Event {
                     event,
                     should_update_ui,
                     should_exit,
                 } => {
+                    if application_state.quit {
+                        save_edited_chart(&application_state);
+                        *should_exit = true;
+                        return;
+                    }
                     if let Some(event) = support::convert_event(
                         &event, &display.gl_window().window()) {
                         // global_input handles aggregation of events and provides these
@@ -93,6 +160,9 @@ pub mod media_player {
                     match event {
                         glium::glutin::event::Event::WindowEvent {
                             event, ..} => match event {
+                                glutin::event::WindowEvent::ReceivedCharacter(character) => {
+                                    key_pressed(&mut application_state, *character);
+                                }
                                 glutin::event::WindowEvent::CloseRequested
                                 | glutin::event::WindowEvent::KeyboardInput {
                                     input:
@@ -102,22 +172,95 @@ pub mod media_player {
                                                 ..
                                         },
                                     ..
-                                } => *should_exit = true,
+                                } => {
+                                    save_edited_chart(&application_state);
+                                    *should_exit = true
+                                }
+                                glutin::event::WindowEvent::KeyboardInput {
+                                    input:
+                                        glium::glutin::event::KeyboardInput {
+                                            state: glutin::event::ElementState::Pressed,
+                                            virtual_keycode:
+                                                Some(glium::glutin::event::VirtualKeyCode::F3),
+                                                ..
+                                        },
+                                    ..
+                                } => {
+                                    application_state.show_perf = !application_state.show_perf;
+                                }
+                                glutin::event::WindowEvent::KeyboardInput {
+                                    input:
+                                        glium::glutin::event::KeyboardInput {
+                                            scancode,
+                                            state,
+                                            ..
+                                        },
+                                    ..
+                                } => match state {
+                                    glutin::event::ElementState::Pressed => {
+                                        application_state.pressed_key = Some((*scancode, Instant::now()));
+                                    }
+                                    glutin::event::ElementState::Released => {
+                                        key_released(&mut application_state, *scancode, Instant::now());
+                                    }
+                                },
                                 _ => {}
                             },
                             _ => {}
                         }
                     }
-                support::Request::SetUi { needs_redraw } => {
-                    set_widgets(ui.set_widgets(), &mut ids, application_state, display);
+                support::Request::Tick { .. } => {
+                    update_game(&mut application_state, &clock, &playbin, &subtitle_capture);
+                }
+                support::Request::SetUi { needs_redraw, animating } => {
+                    // Also when not animating, to draw the latest state
+                    let time = update_game(&mut application_state, &clock, &playbin, &subtitle_capture);
+                    let picking_profile = application_state.picking_profile;
+                    if application_state.show_perf {
+                        application_state.perf.drift = clock.drift();
+                    }
+                    let started = Instant::now();
+                    set_widgets(ui.set_widgets(), &mut ids, &mut application_state, time, &clock, display);
+                    application_state.perf.stage("set_widgets", started.elapsed().as_secs_f64());
+                    if picking_profile && !application_state.picking_profile {
+                        if let Err(err) = playbin.set_state(gstreamer::State::Playing) {
+                            println!("Could not play: {}", err);
+                        }
+                    }
                     *needs_redraw = ui.has_changed();
+                    // Notes move on their own while the media plays, menus
+                    // only change on events
+                    *animating = clock.playing()
+                        && !application_state.finished
+                        && !application_state.picking_profile;
+                    animated = *animating;
                 }
                 support::Request::Redraw => {
+                    let started = Instant::now();
+                    match last_frame.replace(started) {
+                        Some(last_frame) if animated => {
+                            application_state.perf.frame((started - last_frame).as_secs_f64())
+                        }
+                        _ => {}
+                    }
+                    // The primitives are walked as the renderer fills its
+                    // commands
                     let primitives = ui.draw();
                     renderer.fill(display, primitives, &image_map);
+                    let filled = Instant::now();
+                    application_state.perf.stage("ui.draw", (filled - started).as_secs_f64());
                     let mut target = display.draw();
                     target.clear_color(0.0, 0.0, 0.0, 1.0);
                     renderer.draw(display, &mut target, &image_map).unwrap();
+                    let drawn = Instant::now();
+                    application_state.perf.stage("renderer.draw", (drawn - filled).as_secs_f64());
+                    if let Some(frame) = highway_frame(&ui, &ids, &application_state, heard_clock(&application_state, &clock).time()) {
+                        let palette = palette(application_state.profile.theme);
+                        highway_renderer
+                            .draw(display, &mut target, &frame, &palette, [ui.win_w, ui.win_h])
+                            .unwrap();
+                    }
+                    application_state.perf.stage("highway", drawn.elapsed().as_secs_f64());
                     target.finish().unwrap();
                 }
             }
@@ -126,7 +269,7 @@ pub mod media_player {
     }
 
     // GUI Section
-    fn set_widgets(ref mut ui: conrod_core::UiCell, ids: &mut Ids, mut application_state: AppWindow, display: &glium::Display) {
+    fn set_widgets(ref mut ui: conrod_core::UiCell, ids: &mut Ids, application_state: &mut AppWindow, time: f64, clock: &PipelineClock, display: &glium::Display) {
         //let mut application_state_lock = application_state.lock().unwrap();
         let video_controls_length: f64 = 50.0;
         widget::Canvas::new().flow_down(&[
@@ -139,7 +282,7 @@ pub mod media_player {
             // Game area
             (
                 ids.game_area,
-                widget::Canvas::new().color(color::CHARCOAL)
+                widget::Canvas::new().color(palette(application_state.profile.theme).panel)
                     .pad_bottom(20.0)
             ),
             // Video controls area
@@ -198,6 +341,24 @@ pub mod media_player {
         {
         }
 
+        set_score_widgets(ui, ids, application_state);
+        let heard_time = heard_clock(application_state, clock).time();
+        set_ghost_widgets(ui, ids, application_state, heard_time);
+        set_phrase_widgets(ui, ids, application_state);
+        set_lyric_widgets(ui, ids, application_state, time);
+        if application_state.playback.is_some() {
+            set_playback_widgets(ui, ids, application_state, time, clock);
+        }
+        if application_state.finished {
+            set_results_widgets(ui, ids, application_state, clock);
+        }
+        if application_state.picking_profile {
+            set_profile_widgets(ui, ids, application_state);
+        }
+        if application_state.show_perf {
+            set_perf_widgets(ui, ids, application_state);
+        }
+
         // Slider indicator
         // TODO move this circle in glib task and also in the previous loop
         let video_slider_canvas_rect = ui.rect_of(ids.video_slider_canvas).unwrap();
@@ -213,7 +374,6 @@ pub mod media_player {
             .mid_right_of(ids.video_slider_canvas)
             .set(ids.video_slider, ui);
 
-        let playbin: gstreamer::Element;
         let slider_input = ui.widget_input(ids.video_slider);
         // For each click
         // for slider_input_presses in slider_input.clicks() {
@@ -269,6 +429,842 @@ pub mod media_player {
         // }
     }
 
+    // Phrase being typed, the typed part is green (red when there is a
+    // mistake), the letter to type next is highlighted in yellow.
+    fn set_phrase_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &AppWindow) {
+        let gameplay = &application_state.gameplay;
+        let (chart, (index, input)) = match (&gameplay.chart, gameplay.current_phrase()) {
+            (Some(chart), Some(current_phrase)) => (chart, current_phrase),
+            _ => return,
+        };
+        let text: Vec<char> = match chart.notes[index].kind {
+            NoteKind::Phrase(ref phrase) => phrase.text.chars().collect(),
+            _ => return,
+        };
+        let cursor = input.cursor().min(text.len());
+        let typed: String = text[..cursor].iter().collect();
+        let current: String = text[cursor..].iter().take(1).collect();
+        let remaining: String = text[cursor..].iter().skip(1).collect();
+        let font_id = application_state.app_font_id.unwrap();
+        let font_size = 32;
+        let typed_color = if input.is_correct_so_far() { color::GREEN } else { color::RED };
+
+        widget::Text::new(&typed)
+            .font_id(font_id)
+            .font_size(font_size)
+            .color(typed_color)
+            .mid_left_with_margin_on(ids.game_area, 20.0)
+            .set(ids.phrase_typed, ui);
+        widget::Text::new(&current)
+            .font_id(font_id)
+            .font_size(font_size)
+            .color(color::YELLOW)
+            .right_from(ids.phrase_typed, 0.0)
+            .set(ids.phrase_current, ui);
+        widget::Text::new(&remaining)
+            .font_id(font_id)
+            .font_size(font_size)
+            .color(color::WHITE)
+            .right_from(ids.phrase_current, 0.0)
+            .set(ids.phrase_remaining, ui);
+    }
+
+    // The score over the highway, which is drawn after the UI by the
+    // highway renderer.
+    fn set_score_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &AppWindow) {
+        let gameplay = &application_state.gameplay;
+        if gameplay.chart.is_none() {
+            return;
+        }
+        let palette = palette(application_state.profile.theme);
+        widget::Text::new(&format!("Score: {}", gameplay.score.points))
+            .font_id(application_state.app_font_id.unwrap())
+            .font_size(16)
+            .color(palette.text)
+            .top_right_with_margin_on(ids.game_area, 10.0)
+            .set(ids.score, ui);
+    }
+
+    // Key and hold notes scroll down their key's lane to the judgement line,
+    // with the last judgement and the combo over them. Drawn after the UI,
+    // except under the results and the profile picker.
+    fn highway_frame(ui: &conrod_core::Ui, ids: &Ids, application_state: &AppWindow, time: f64) -> Option<highway::Frame> {
+        if application_state.finished || application_state.picking_profile {
+            return None;
+        }
+        let area = ui.rect_of(ids.game_area)?;
+        let area = highway::Rect { x: area.x(), y: area.y(), w: area.w(), h: area.h() };
+        Some(highway::frame(&application_state.gameplay, &application_state.profile, area, time))
+    }
+
+    // Score and combo of the ghost under the live score, how far ahead the
+    // player is, and how the last note went compared to the ghost.
+    fn set_ghost_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &AppWindow, time: f64) {
+        let ghost = match application_state.ghost {
+            Some(ref ghost) => ghost,
+            None => return,
+        };
+        let live = &application_state.gameplay;
+        let font_id = application_state.app_font_id.unwrap();
+        widget::Text::new(&format!("Ghost: {}  {} combo", ghost.gameplay.score.points, ghost.gameplay.score.combo))
+            .font_id(font_id)
+            .font_size(14)
+            .color(color::LIGHT_GREY)
+            .down_from(ids.score, 6.0)
+            .align_right_of(ids.score)
+            .set(ids.ghost_score, ui);
+        let lead = ghost.lead(live);
+        let lead_color = match lead.cmp(&0) {
+            Ordering::Greater => color::GREEN,
+            Ordering::Less => color::RED,
+            Ordering::Equal => color::WHITE,
+        };
+        widget::Text::new(&format!("{:+}", lead))
+            .font_id(font_id)
+            .font_size(14)
+            .color(lead_color)
+            .down_from(ids.ghost_score, 6.0)
+            .align_right_of(ids.ghost_score)
+            .set(ids.ghost_lead, ui);
+
+        // Shown with the flash on the highway, drawn ahead by the visual offset
+        let time = time + application_state.profile.visual_offset * live.rate();
+        let last = live
+            .judgements()
+            .last()
+            .filter(|judged| judged.time <= time && time - judged.time < JUDGEMENT_SHOWN);
+        let comparison = last.and_then(|judged| ghost.compare_note(live, judged.note));
+        let (text, note_color) = match comparison {
+            Some(Ordering::Greater) => ("ahead", color::GREEN),
+            Some(Ordering::Less) => ("behind", color::RED),
+            Some(Ordering::Equal) => ("even", color::LIGHT_GREY),
+            None => return,
+        };
+        let area = ui.rect_of(ids.game_area).unwrap();
+        widget::Text::new(text)
+            .font_id(font_id)
+            .font_size(14)
+            .color(note_color)
+            .x_y(area.x(), area.bottom() + JUDGEMENT_LINE_MARGIN + 120.0)
+            .set(ids.ghost_note, ui);
+    }
+
+    // Results Section
+    // Shown over everything once the media ended: the judgement breakdown on
+    // the left, the hit offsets, the accuracy along the song and the high
+    // scores on the right.
+    fn set_results_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &mut AppWindow, clock: &PipelineClock) {
+        let font_id = application_state.app_font_id.unwrap();
+        let gameplay = &application_state.gameplay;
+        let results = Results::new(gameplay, HISTOGRAM_BINS, RESULT_SECTIONS);
+        let title = gameplay.chart.as_ref().map(|chart| chart.metadata.title.clone()).unwrap_or_default();
+        let palette = palette(application_state.profile.theme);
+
+        widget::Canvas::new()
+            .color(palette.background)
+            .wh_of(ids.master)
+            .middle_of(ids.master)
+            .set(ids.results, ui);
+        let area = ui.rect_of(ids.results).unwrap();
+
+        let mut summary = format!(
+            "{}\n\n{} points\n{:.2}% accuracy\n{} max combo\n\n",
+            title,
+            results.score.points,
+            results.accuracy * 100.0,
+            results.score.max_combo
+        );
+        let rate = gameplay.rate();
+        if rate != 1.0 {
+            summary += &format!("Practice at {:.0}% speed\n\n", rate * 100.0);
+        }
+        for judgement in Judgement::ALL.iter() {
+            summary += &format!("{}: {}\n", judgement.name(), results.score.count(*judgement));
+        }
+        if let (Some(mean), Some(unstable_rate)) = (results.mean_offset, results.unstable_rate) {
+            summary += &format!("\nMean offset: {:+.1} ms\nUnstable rate: {:.1}", mean * 1000.0, unstable_rate);
+        }
+        widget::Text::new(&summary)
+            .font_id(font_id)
+            .font_size(16)
+            .color(palette.text)
+            .line_spacing(4.0)
+            .top_left_with_margins_on(ids.results, 30.0, 30.0)
+            .set(ids.results_summary, ui);
+        widget::Text::new(results.grade)
+            .font_id(font_id)
+            .font_size(72)
+            .color(color::YELLOW)
+            .top_left_with_margins_on(ids.results, 30.0, area.w() / 2.0 - 120.0)
+            .set(ids.results_grade, ui);
+
+        // Graphs on the right half
+        let graph_w = area.w() / 2.0 - 40.0;
+        let graph_h = 120.0;
+        let graph_x = area.x() + area.w() / 4.0;
+
+        let histogram_bottom = area.top() - 60.0 - graph_h;
+        widget::Text::new("Hit offsets, early to late")
+            .font_id(font_id)
+            .font_size(12)
+            .color(palette.dim_text)
+            .x_y(graph_x, area.top() - 40.0)
+            .set(ids.results_histogram_label, ui);
+        let highest = results.histogram.iter().cloned().max().unwrap_or(0).max(1);
+        let bar_w = graph_w / results.histogram.len() as f64;
+        ids.results_histogram.resize(results.histogram.len(), &mut ui.widget_id_generator());
+        for (bin, (&count, &id)) in results.histogram.iter().zip(ids.results_histogram.iter()).enumerate() {
+            let h = (count as f64 / highest as f64 * graph_h).max(1.0);
+            widget::Rectangle::fill([bar_w - 1.0, h])
+                .color(color::LIGHT_BLUE)
+                .x_y(graph_x - graph_w / 2.0 + (bin as f64 + 0.5) * bar_w, histogram_bottom + h / 2.0)
+                .set(id, ui);
+        }
+        widget::Rectangle::fill([1.0, graph_h])
+            .color(palette.text)
+            .x_y(graph_x, histogram_bottom + graph_h / 2.0)
+            .set(ids.results_histogram_zero, ui);
+
+        let sections_bottom = histogram_bottom - 60.0 - graph_h;
+        widget::Text::new("Accuracy along the song")
+            .font_id(font_id)
+            .font_size(12)
+            .color(palette.dim_text)
+            .x_y(graph_x, histogram_bottom - 40.0)
+            .set(ids.results_sections_label, ui);
+        let bar_w = graph_w / results.sections.len() as f64;
+        ids.results_sections.resize(results.sections.len(), &mut ui.widget_id_generator());
+        for (section, (accuracy, &id)) in results.sections.iter().zip(ids.results_sections.iter()).enumerate() {
+            let (h, bar_color) = match accuracy {
+                Some(accuracy) => ((accuracy * graph_h).max(1.0), color::GREEN),
+                None => (1.0, color::DARK_GREY),
+            };
+            widget::Rectangle::fill([bar_w - 1.0, h])
+                .color(bar_color)
+                .x_y(graph_x - graph_w / 2.0 + (section as f64 + 0.5) * bar_w, sections_bottom + h / 2.0)
+                .set(id, ui);
+        }
+
+        if let Some(text) = leaderboard_text(application_state) {
+            widget::Text::new(&text)
+                .font_id(font_id)
+                .font_size(12)
+                .color(palette.text)
+                .line_spacing(2.0)
+                .top_left_with_margins_on(ids.results, area.top() - sections_bottom + 20.0, area.w() / 2.0 + 20.0)
+                .set(ids.results_leaderboard, ui);
+        }
+
+        // Buttons
+        let saved = application_state.replay_path.is_some();
+        let mut retry = false;
+        for _click in widget::Button::new()
+            .color(palette.button)
+            .label("Retry")
+            .label_color(palette.text)
+            .label_font_id(font_id)
+            .w_h(120.0, 36.0)
+            .bottom_left_with_margins_on(ids.results, 30.0, 30.0)
+            .set(ids.results_retry, ui)
+        {
+            retry = true;
+        }
+        for _click in widget::Button::new()
+            .color(palette.button)
+            .label("Back")
+            .label_color(palette.text)
+            .label_font_id(font_id)
+            .w_h(120.0, 36.0)
+            .right_from(ids.results_retry, 20.0)
+            .set(ids.results_back, ui)
+        {
+            application_state.quit = true;
+        }
+        for _click in widget::Button::new()
+            .color(palette.button)
+            .label(if saved { "Replay saved" } else { "Save replay" })
+            .label_color(if saved { palette.dim_text } else { palette.text })
+            .label_font_id(font_id)
+            .w_h(160.0, 36.0)
+            .right_from(ids.results_back, 20.0)
+            .set(ids.results_save, ui)
+        {
+            if !saved {
+                save_replay(application_state);
+            }
+        }
+        if retry {
+            restart(application_state, clock);
+        }
+    }
+
+    // Play the chart again from the start
+    fn restart(application_state: &mut AppWindow, clock: &PipelineClock) {
+        // Replays play again from the start on their own when going back
+        if application_state.playback.is_none() {
+            let gameplay = &application_state.gameplay;
+            let mut restarted = Gameplay::new(gameplay.chart.clone());
+            restarted.windows = gameplay.windows;
+            restarted.mods = gameplay.mods.clone();
+            application_state.gameplay = restarted;
+        }
+        application_state.finished = false;
+        application_state.replay_path = None;
+        clock.seek(0.0, clock.rate());
+    }
+
+    // Replay Section
+    // Seek bar over the game area and playback speed next to it
+    fn set_playback_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &AppWindow, time: f64, clock: &PipelineClock) {
+        let font_id = application_state.app_font_id.unwrap();
+        let duration = clock.duration().unwrap_or(0.0).max(time);
+        let area = ui.rect_of(ids.game_area).unwrap();
+        let speed_width = 160.0;
+        if let Some(position) = widget::Slider::new(time, 0.0, duration)
+            .w_h(area.w() - speed_width - 30.0, 16.0)
+            .top_left_with_margin_on(ids.game_area, 10.0)
+            .color(color::DARK_RED)
+            .label(&format!("{:.1} / {:.1}", time, duration))
+            .label_font_id(font_id)
+            .label_font_size(10)
+            .label_color(color::WHITE)
+            .set(ids.replay_seek, ui)
+        {
+            clock.seek(position, clock.rate());
+        }
+        if let Some(rate) = widget::Slider::new(clock.rate(), MIN_PLAYBACK_SPEED, MAX_PLAYBACK_SPEED)
+            .w_h(speed_width, 16.0)
+            .right_from(ids.replay_seek, 10.0)
+            .color(color::DARK_BLUE)
+            .label(&format!("Speed {:.2}x", clock.rate()))
+            .label_font_id(font_id)
+            .label_font_size(10)
+            .label_color(color::WHITE)
+            .set(ids.replay_speed, ui)
+        {
+            // Steps of 5% so the speed can be set back to 1x
+            clock.seek(time, (rate * 20.0).round() / 20.0);
+        }
+    }
+
+    // Current and next lyric line over the bottom of the video, the sung
+    // part of the current line is filled in yellow like karaoke.
+    fn set_lyric_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &AppWindow, time: f64) {
+        let (current, next) = application_state.lyrics.lines_at(time);
+        let font_id = application_state.app_font_id.unwrap();
+        let font_size = 24;
+
+        if let Some(current) = current {
+            let sung: String = current.text.chars().take(current.sung_characters(time)).collect();
+            widget::Text::new(&current.text)
+                .font_id(font_id)
+                .font_size(font_size)
+                .color(color::WHITE)
+                .mid_bottom_with_margin_on(ids.video_area, 50.0)
+                .set(ids.lyric_current, ui);
+            // The font is monospaced, so the sung part lines up with the
+            // start of the whole line
+            widget::Text::new(&sung)
+                .font_id(font_id)
+                .font_size(font_size)
+                .color(color::YELLOW)
+                .top_left_of(ids.lyric_current)
+                .set(ids.lyric_sung, ui);
+        }
+        if let Some(next) = next {
+            widget::Text::new(&next.text)
+                .font_id(font_id)
+                .font_size(font_size * 3 / 4)
+                .color(color::LIGHT_GREY)
+                .mid_bottom_with_margin_on(ids.video_area, 20.0)
+                .set(ids.lyric_next, ui);
+        }
+    }
+
+    // Game Section
+    // Keys, notes expiring, the ghost and the end of the media, on every tick
+    // of the loop. Returns the media time, the one the seek bar and the lyrics
+    // are drawn at; the highway is drawn on the heard clock.
+    fn update_game(application_state: &mut AppWindow, clock: &PipelineClock, playbin: &gstreamer::Element, subtitle_capture: &Option<SubtitleCapture>) -> f64 {
+        if let Some(ref subtitle_capture) = subtitle_capture {
+            add_subtitle_cues(application_state, subtitle_capture);
+        }
+        // The first seek once playing sets the practice rate
+        if clock.playing() {
+            if let Some(rate) = application_state.pending_rate.take() {
+                clock.seek(clock.time(), rate);
+            }
+        }
+        let heard = heard_clock(application_state, clock);
+        play_queued_keys(application_state, &heard);
+        let heard_time = heard.time();
+        match application_state.playback {
+            Some(ref mut playback) => playback.seek(&mut application_state.gameplay, heard_time),
+            None => application_state.gameplay.update(heard_time),
+        }
+        if let Some(ref mut ghost) = application_state.ghost {
+            ghost.update(heard_time);
+        }
+        let time = clock.time();
+        if !application_state.finished && end_of_stream(playbin) {
+            application_state.finished = true;
+            // Notes still in their windows when the media ends are judged
+            // before the play is saved
+            application_state.gameplay.finish();
+            save_replay(application_state);
+            record_score(application_state);
+        }
+        time
+    }
+
+    // What the player hears, late by the audio offset of the profile in real
+    // time. Keys and notes are timed on it, and the highway is drawn on it.
+    fn heard_clock<'a>(application_state: &AppWindow, clock: &'a PipelineClock) -> OffsetClock<'a, PipelineClock> {
+        let audio_offset = application_state.profile.audio_offset * application_state.gameplay.rate();
+        OffsetClock::new(clock, audio_offset)
+    }
+
+    // The character comes after the key press event, the key it came from is
+    // remembered to know when it is released. Keys are queued with the
+    // moment they were pressed, and played at the next update.
+    fn key_pressed(application_state: &mut AppWindow, character: char) {
+        if application_state.playback.is_some() || application_state.picking_profile {
+            return;
+        }
+        // Keys play the chart key they are bound to, but are recorded as
+        // typed when editing
+        let key = match application_state.editing {
+            Some(_) => character,
+            None => application_state.profile.key(character),
+        };
+        match application_state.pressed_key.take() {
+            // Keys held down repeat their character
+            Some((scancode, _)) if application_state.held_keys.iter().any(|(held, _)| *held == scancode) => {}
+            pressed => {
+                let instant = match pressed {
+                    Some((scancode, instant)) => {
+                        application_state.held_keys.push((scancode, key));
+                        instant
+                    }
+                    None => Instant::now(),
+                };
+                application_state.input_queue.push(instant, key, KeyAction::Down);
+            }
+        }
+    }
+
+    fn key_released(application_state: &mut AppWindow, scancode: u32, instant: Instant) {
+        if let Some(position) = application_state.held_keys.iter().position(|(held, _)| *held == scancode) {
+            let (_, key) = application_state.held_keys.remove(position);
+            application_state.input_queue.push(instant, key, KeyAction::Up);
+        }
+    }
+
+    fn play_queued_keys(application_state: &mut AppWindow, clock: &OffsetClock<PipelineClock>) {
+        for (instant, input) in application_state.input_queue.drain_timed(clock) {
+            match (&application_state.editing, input.action) {
+                (Some(_), KeyAction::Down) => record_key(application_state, input.key, input.time),
+                (Some(_), KeyAction::Up) => {}
+                (None, _) => {
+                    let judged = application_state.gameplay.judgements().len();
+                    application_state.gameplay.input(&input);
+                    // Only hits are the key's doing, misses may be given on
+                    // the way
+                    let hit = application_state.gameplay.judgements()[judged..]
+                        .iter()
+                        .any(|judged| judged.offset.is_some());
+                    if hit {
+                        application_state.perf.input_latency = Some(instant.elapsed().as_secs_f64());
+                    }
+                }
+            }
+        }
+    }
+
+    // Subtitle cues are shown as lyrics and, unless a chart was loaded, added
+    // to the chart as phrase notes
+    fn add_subtitle_cues(application_state: &mut AppWindow, subtitle_capture: &SubtitleCapture) {
+        for cue in subtitle_capture.drain() {
+            if application_state.use_subtitle_chart {
+                application_state.gameplay.add_note(subtitles::to_note(&cue));
+            }
+            application_state.lyrics.add_line(cue);
+        }
+    }
+
+    fn end_of_stream(playbin: &gstreamer::Element) -> bool {
+        playbin
+            .get_bus()
+            .and_then(|bus| bus.pop_filtered(&[gstreamer::MessageType::Eos]))
+            .is_some()
+    }
+
+    // Replays are saved in the replays folder of the working directory
+    fn save_replay(application_state: &mut AppWindow) {
+        if application_state.editing.is_some() || application_state.playback.is_some() {
+            return;
+        }
+        let replay = match application_state.gameplay.replay() {
+            Some(replay) => replay,
+            None => return,
+        };
+        let directory = Path::new(REPLAY_DIRECTORY);
+        let path = replay::replay_path(directory, &replay.title, timestamp());
+        let saved = fs::create_dir_all(directory)
+            .map_err(anyhow::Error::from)
+            .and_then(|_| replay.save(&path));
+        match saved {
+            Ok(()) => {
+                println!("Replay saved to {}", path.display());
+                application_state.replay_path = Some(path);
+            }
+            Err(err) => println!("{:#}", err),
+        }
+    }
+
+    // Unix seconds
+    fn timestamp() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0)
+    }
+
+    // High Scores Section
+    // Plays are only recorded when the database could be read, so that a
+    // file of a newer version is left alone. The player is told why.
+    fn open_scores(application_state: &mut AppWindow) {
+        match ScoreDatabase::open(Path::new(SCORES_FILE)) {
+            Ok(scores) => {
+                if scores.skipped > 0 {
+                    println!("Skipped {} unreadable plays in {}", scores.skipped, SCORES_FILE);
+                }
+                application_state.scores = Some(scores);
+            }
+            Err(err) => {
+                println!("{:#}", err);
+                application_state.scores_error = Some(format!("{:#}", err));
+            }
+        }
+    }
+
+    // Every play of a chart with the keyboard is recorded, replays and edits
+    // are not
+    fn record_score(application_state: &mut AppWindow) {
+        if application_state.editing.is_some() || application_state.playback.is_some() {
+            return;
+        }
+        let gameplay = &application_state.gameplay;
+        let chart = match gameplay.chart {
+            Some(ref chart) => chart,
+            None => return,
+        };
+        let entry = Entry::new(
+            chart,
+            &application_state.profile.name,
+            gameplay.score.clone(),
+            gameplay.mods.clone(),
+            timestamp(),
+            application_state.replay_path.clone(),
+        );
+        if let Some(ref mut scores) = application_state.scores {
+            if let Err(err) = scores.add(entry) {
+                println!("{:#}", err);
+                application_state.scores_error = Some(format!("{:#}", err));
+            }
+        }
+    }
+
+    // Personal best of the profile and best plays of everyone on the chart,
+    // after why the play was not recorded if it was not
+    fn leaderboard_text(application_state: &AppWindow) -> Option<String> {
+        let error = application_state
+            .scores_error
+            .as_ref()
+            .map(|err| format!("Plays are not recorded: {}\n\n", err));
+        let scores = match application_state.scores {
+            Some(ref scores) => scores,
+            None => return error,
+        };
+        let chart = application_state.gameplay.chart.as_ref()?;
+        let hash = chart.hash();
+        let mut text = error.unwrap_or_default();
+        text += &match scores.personal_best(hash, &application_state.profile.name) {
+            Some(best) => {
+                // Ties go to the earlier play, so the last play recorded is
+                // only the best when it beat it
+                let new = application_state.playback.is_none()
+                    && scores.entries.last().map_or(false, |last| std::ptr::eq(last, best));
+                format!(
+                    "Personal best: {} points, {:.2}%{}{}\n\n",
+                    best.score.points,
+                    best.accuracy * 100.0,
+                    mods_text(&best.mods),
+                    if new { " (new!)" } else { "" }
+                )
+            }
+            None => String::from("No personal best yet\n\n"),
+        };
+        let difficulty = if chart.metadata.difficulty.is_empty() { "Leaderboard" } else { &chart.metadata.difficulty };
+        text += &format!("{}, top {}\n", difficulty, LEADERBOARD_SIZE);
+        for (rank, entry) in scores.top(hash, None, LEADERBOARD_SIZE).iter().enumerate() {
+            text += &format!(
+                "{}. {} - {} points, {:.2}%{}\n",
+                rank + 1,
+                entry.profile,
+                entry.score.points,
+                entry.accuracy * 100.0,
+                mods_text(&entry.mods)
+            );
+        }
+        Some(text)
+    }
+
+    // Plays with mods, practice at a reduced rate among them, are marked
+    // with them
+    fn mods_text(mods: &[String]) -> String {
+        if mods.is_empty() {
+            String::new()
+        } else {
+            format!(" +{}", mods.join(","))
+        }
+    }
+
+    // Profiles Section
+    // Like the scores, the profiles are only saved when they could be read
+    pub fn open_profiles() -> Option<Profiles> {
+        match Profiles::load(Path::new(PROFILES_FILE)) {
+            Ok(profiles) => Some(profiles),
+            Err(err) => {
+                println!("{:#}", err);
+                None
+            }
+        }
+    }
+
+    // Play with the profile `name`, or the last one used when empty, and
+    // remember it for the next time
+    pub fn select_profile(application_state: &mut AppWindow, name: &str) {
+        let name = match name {
+            "" => application_state
+                .profiles
+                .as_ref()
+                .and_then(|profiles| profiles.last.clone())
+                .unwrap_or_else(|| profile::DEFAULT_PROFILE.to_string()),
+            name => name.to_string(),
+        };
+        let selected = match application_state.profiles {
+            Some(ref mut profiles) => profiles.select(&name).map(|profile| profile.clone()),
+            None => Ok(profile::Profile::new(&name)),
+        };
+        match selected {
+            Ok(profile) => {
+                application_state.profile = profile;
+                application_state.picking_profile = false;
+                save_profiles(application_state);
+            }
+            Err(err) => println!("{:#}", err),
+        }
+    }
+
+    pub fn save_profiles(application_state: &AppWindow) {
+        if let Some(ref profiles) = application_state.profiles {
+            if let Err(err) = profiles.save(Path::new(PROFILES_FILE)) {
+                println!("{:#}", err);
+            }
+        }
+    }
+
+    // Shown over everything at startup, while the media waits: a button per
+    // profile, the last one used first, and a box to name a new one
+    fn set_profile_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &mut AppWindow) {
+        let font_id = application_state.app_font_id.unwrap();
+        // No profile yet to take the theme from
+        let palette = palette(Theme::Dark);
+        widget::Canvas::new()
+            .color(palette.background)
+            .wh_of(ids.master)
+            .middle_of(ids.master)
+            .set(ids.profiles, ui);
+        widget::Text::new("Who is playing?")
+            .font_id(font_id)
+            .font_size(24)
+            .color(palette.text)
+            .mid_top_with_margin_on(ids.profiles, 40.0)
+            .set(ids.profiles_title, ui);
+
+        let mut names: Vec<String> = application_state
+            .profiles
+            .as_ref()
+            .map(|profiles| profiles.profiles.iter().map(|profile| profile.name.clone()).collect())
+            .unwrap_or_default();
+        let last = application_state.profiles.as_ref().and_then(|profiles| profiles.last.clone());
+        if let Some(position) = names.iter().position(|name| Some(name) == last.as_ref()) {
+            let name = names.remove(position);
+            names.insert(0, name);
+        }
+        let mut selected = None;
+        ids.profiles_buttons.resize(names.len(), &mut ui.widget_id_generator());
+        for (index, (name, &id)) in names.iter().zip(ids.profiles_buttons.iter()).enumerate() {
+            let button = widget::Button::new()
+                .color(if Some(name) == last.as_ref() { color::DARK_BLUE } else { palette.button })
+                .label(name)
+                .label_color(palette.text)
+                .label_font_id(font_id)
+                .w_h(240.0, 32.0);
+            let button = match index {
+                0 => button.down_from(ids.profiles_title, 30.0),
+                _ => button.down(8.0),
+            };
+            for _click in button.set(id, ui) {
+                selected = Some(name.clone());
+            }
+        }
+
+        let hint = widget::Text::new(if names.is_empty() {
+            "Type your name and press Enter"
+        } else {
+            "Or type a new name and press Enter"
+        })
+        .font_id(font_id)
+        .font_size(12)
+        .color(palette.dim_text);
+        let hint = match ids.profiles_buttons.last() {
+            Some(&button) => hint.down_from(button, 30.0),
+            None => hint.down_from(ids.profiles_title, 30.0),
+        };
+        hint.set(ids.profiles_hint, ui);
+        for event in widget::TextBox::new(&application_state.new_profile_name)
+            .font_id(font_id)
+            .font_size(16)
+            .color(palette.panel)
+            .text_color(palette.text)
+            .w_h(240.0, 32.0)
+            .down_from(ids.profiles_hint, 8.0)
+            .set(ids.profiles_new, ui)
+        {
+            match event {
+                widget::text_box::Event::Update(text) => application_state.new_profile_name = text,
+                widget::text_box::Event::Enter => {
+                    let name = application_state.new_profile_name.trim().to_string();
+                    if profile::valid_name(&name) {
+                        selected = Some(name);
+                    }
+                }
+            }
+        }
+        if let Some(name) = selected {
+            select_profile(application_state, &name);
+        }
+    }
+
+    // Performance Section
+    // Frames per second, a graph of the last frame times against the time of
+    // a 60 FPS frame, the time of each stage of a frame, and how late the
+    // game clock and the last key judged are. Toggled with F3.
+    fn set_perf_widgets(ui: &mut conrod_core::UiCell, ids: &mut Ids, application_state: &AppWindow) {
+        let font_id = application_state.app_font_id.unwrap();
+        let perf = &application_state.perf;
+        let mut text = match (perf.fps(), perf.worst_frame()) {
+            (Some(fps), Some(worst)) => format!("{:.0} FPS, worst frame {:.1} ms\n", fps, worst * 1000.0),
+            _ => "No frames timed while playing\n".to_string(),
+        };
+        for (stage, time) in perf.stages() {
+            text += &format!("{}: {:.2} ms\n", stage, time * 1000.0);
+        }
+        text += &match perf.drift {
+            Some(drift) => format!("A/V drift: {:+.1} ms\n", drift * 1000.0),
+            None => "A/V drift: -\n".to_string(),
+        };
+        text += &match perf.input_latency {
+            Some(latency) => format!("Input to judgement: {:.1} ms", latency * 1000.0),
+            None => "Input to judgement: -".to_string(),
+        };
+
+        widget::Canvas::new()
+            .w_h(PERF_WIDTH, PERF_HEIGHT)
+            .top_left_with_margin_on(ids.video_area, 10.0)
+            .color(color::BLACK.alpha(0.7))
+            .set(ids.perf, ui);
+        widget::Text::new(&text)
+            .font_id(font_id)
+            .font_size(11)
+            .color(color::WHITE)
+            .top_left_with_margins_on(ids.perf, 8.0, 8.0)
+            .set(ids.perf_text, ui);
+
+        // Frame times from the oldest on the left, the top of the graph
+        // twice a 60 FPS frame
+        let area = ui.rect_of(ids.perf).unwrap();
+        let (left, bottom) = (area.left() + 8.0, area.bottom() + 8.0);
+        let step = (area.w() - 16.0) / perf::HISTORY as f64;
+        let height = |time: f64| bottom + (time / PERF_GRAPH_TOP).min(1.0) * PERF_GRAPH_HEIGHT;
+        let points: Vec<Point> = perf
+            .frame_times()
+            .enumerate()
+            .map(|(index, time)| [left + index as f64 * step, height(time)])
+            .collect();
+        let target = height(PERF_GRAPH_TOP / 2.0);
+        widget::Line::abs([left, target], [area.right() - 8.0, target])
+            .color(color::DARK_GREY)
+            .set(ids.perf_target, ui);
+        if points.len() > 1 {
+            widget::PointPath::abs(points)
+                .color(color::GREEN)
+                .set(ids.perf_graph, ui);
+        }
+    }
+
+    // Theme Section
+    pub struct Palette {
+        pub background: color::Color,
+        pub panel: color::Color,
+        pub button: color::Color,
+        pub text: color::Color,
+        pub dim_text: color::Color,
+    }
+
+    pub fn palette(theme: Theme) -> Palette {
+        match theme {
+            Theme::Dark => Palette {
+                background: color::BLACK,
+                panel: color::CHARCOAL,
+                button: color::CHARCOAL,
+                text: color::WHITE,
+                dim_text: color::LIGHT_GREY,
+            },
+            Theme::Light => Palette {
+                background: color::WHITE,
+                panel: color::LIGHT_GREY,
+                button: color::LIGHT_GREY,
+                text: color::BLACK,
+                dim_text: color::DARK_GREY,
+            },
+        }
+    }
+
+    // Edit Section
+    // While editing, every character typed becomes a key note at the media time
+    fn record_key(application_state: &mut AppWindow, character: char, time: f64) {
+        if character.is_control() {
+            return;
+        }
+        application_state
+            .gameplay
+            .chart
+            .get_or_insert_with(Chart::default)
+            .add_note(Note::key(time, character));
+    }
+
+    fn save_edited_chart(application_state: &AppWindow) {
+        if let (Some(path), Some(chart)) = (&application_state.editing, &application_state.gameplay.chart) {
+            match chart.save(path) {
+                Ok(()) => println!("Chart saved to {}", path.display()),
+                Err(err) => println!("{:#}", err),
+            }
+        }
+    }
+
     fn get_video_location_as_percent(playbin: &gstreamer::Element) 
         -> f64 {
         if let Some(pos) = playbin.query_position::<gstreamer::ClockTime>() {
@@ -282,11 +1278,9 @@ pub mod media_player {
         return 0.0;
     }
 
-    fn start_gstreamer(display: &glium::Display) {
+    // `preserve_pitch` keeps the pitch of the audio played slower or faster
+    fn start_gstreamer(display: &glium::Display, uri: &str, preserve_pitch: bool) -> (gstreamer::Element, Option<SubtitleCapture>) {
         gstreamer::init().unwrap();
-        //let uri = "file:///c:/Videos/1280.mp4";
-        let uri = "https://www.freedesktop.org/software/gstreamer-sdk/\
-                   data/media/sintel_trailer-480p.webm";
 
         let playbin = 
             gstreamer::ElementFactory::make("playbin", None).unwrap_or_else(|err| {
@@ -312,6 +1306,22 @@ pub mod media_player {
             println!("{:?}", err);
         });
 
+        if preserve_pitch {
+            match gstreamer::ElementFactory::make("scaletempo", None) {
+                Ok(scaletempo) => {
+                    if let Err(err) = playbin.set_property("audio-filter", &scaletempo) {
+                        println!("Could not keep the pitch: {}", err);
+                    }
+                }
+                Err(_) => println!("scaletempo is missing, the pitch changes with the rate"),
+            }
+        }
+
+        // Embedded subtitles become a typing chart
+        let subtitle_capture = SubtitleCapture::attach(&playbin)
+            .map_err(|err| println!("Could not capture the subtitles: {:?}", err))
+            .ok();
+
         playbin
             .connect("video-tags-changed", false, |args| {
                 let pipeline = args[0]
@@ -395,6 +1405,8 @@ pub mod media_player {
             .expect("Unable to set the playbin to the 'Playing State'");
         
         bus.remove_signal_watch();
+
+        (playbin, subtitle_capture)
     }
 
     // We are possibly in a GStreamer working thread, so we notify the main
@@ -447,7 +1459,46 @@ pub mod media_player {
             video_buttons_canvas,
             video_slider_indicator,
             circle,
-            text
+            text,
+            // Phrase notes
+            phrase_typed,
+            phrase_current,
+            phrase_remaining,
+            // Highway
+            score,
+            // Ghost
+            ghost_score,
+            ghost_lead,
+            ghost_note,
+            // Results
+            results,
+            results_summary,
+            results_grade,
+            results_histogram_label,
+            results_histogram[],
+            results_histogram_zero,
+            results_sections_label,
+            results_sections[],
+            results_leaderboard,
+            profiles,
+            profiles_title,
+            profiles_buttons[],
+            profiles_hint,
+            profiles_new,
+            perf,
+            perf_text,
+            perf_target,
+            perf_graph,
+            results_retry,
+            results_back,
+            results_save,
+            // Replay
+            replay_seek,
+            replay_speed,
+            // Lyrics
+            lyric_current,
+            lyric_sung,
+            lyric_next,
 
         }
     }
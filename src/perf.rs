@@ -0,0 +1,76 @@
+// Performance counters for the debug overlay: how long the last frames took,
+// how long each stage of them took, how far the game clock is from the media
+// position and how long the last key took to be judged. All in seconds.
+use std::collections::VecDeque;
+
+/// Frames remembered for the graph and the averages.
+pub const HISTORY: usize = 120;
+
+#[derive(Clone, Debug, Default)]
+pub struct FrameStats {
+    frames: VecDeque<f64>,
+    // In the order they were first timed
+    stages: Vec<(&'static str, VecDeque<f64>)>,
+    /// Media position minus game clock time, when last measured.
+    pub drift: Option<f64>,
+    /// From a key arriving to its note being judged, for the last hit.
+    pub input_latency: Option<f64>,
+}
+
+impl FrameStats {
+    pub fn new() -> FrameStats {
+        FrameStats::default()
+    }
+
+    /// A frame drawn `duration` after the one before.
+    pub fn frame(&mut self, duration: f64) {
+        push(&mut self.frames, duration);
+    }
+
+    /// `duration` spent in `stage` this frame.
+    pub fn stage(&mut self, stage: &'static str, duration: f64) {
+        match self.stages.iter_mut().find(|(name, _)| *name == stage) {
+            Some((_, times)) => push(times, duration),
+            None => {
+                let mut times = VecDeque::with_capacity(HISTORY);
+                times.push_back(duration);
+                self.stages.push((stage, times));
+            }
+        }
+    }
+
+    /// Durations of the last frames, oldest first.
+    pub fn frame_times(&self) -> impl Iterator<Item = f64> + '_ {
+        self.frames.iter().cloned()
+    }
+
+    /// Frames per second over the last frames.
+    pub fn fps(&self) -> Option<f64> {
+        let total: f64 = self.frames.iter().sum();
+        if total > 0.0 {
+            Some(self.frames.len() as f64 / total)
+        } else {
+            None
+        }
+    }
+
+    /// Longest of the last frames.
+    pub fn worst_frame(&self) -> Option<f64> {
+        self.frames.iter().cloned().fold(None, |worst, time| Some(worst.map_or(time, |worst: f64| worst.max(time))))
+    }
+
+    /// Every stage timed with its mean over the last frames.
+    pub fn stages(&self) -> Vec<(&'static str, f64)> {
+        self.stages
+            .iter()
+            .map(|(name, times)| (*name, times.iter().sum::<f64>() / times.len() as f64))
+            .collect()
+    }
+}
+
+fn push(times: &mut VecDeque<f64>, time: f64) {
+    if times.len() == HISTORY {
+        times.pop_front();
+    }
+    times.push_back(time);
+}
@@ -0,0 +1,103 @@
+// Game clock of the player: the position of the playbin, interpolated with
+// the pipeline clock between queries.
+use std::cell::Cell;
+use std::time::Instant;
+
+use gstreamer::prelude::*;
+
+use mechanical::clock::{GameClock, Interpolation};
+
+pub struct PipelineClock {
+    playbin: gstreamer::Element,
+    interpolation: Cell<Interpolation>,
+}
+
+impl PipelineClock {
+    pub fn new(playbin: &gstreamer::Element) -> PipelineClock {
+        PipelineClock {
+            playbin: playbin.clone(),
+            interpolation: Cell::new(Interpolation::default()),
+        }
+    }
+
+    pub fn duration(&self) -> Option<f64> {
+        self.playbin
+            .query_duration::<gstreamer::ClockTime>()
+            .and_then(|duration| duration.nseconds())
+            .map(seconds)
+    }
+
+    /// Seek the media to `time`, playing at `rate` times the normal speed.
+    pub fn seek(&self, time: f64, rate: f64) {
+        let position = gstreamer::ClockTime::from_nseconds((time.max(0.0) * 1_000_000_000.0) as u64);
+        let seeked = self.playbin.seek(
+            rate,
+            gstreamer::SeekFlags::FLUSH | gstreamer::SeekFlags::ACCURATE,
+            gstreamer::SeekType::Set,
+            position,
+            gstreamer::SeekType::None,
+            gstreamer::ClockTime::none(),
+        );
+        match seeked {
+            Ok(()) => {
+                let mut interpolation = self.interpolation.get();
+                interpolation.seek(time, rate);
+                self.interpolation.set(interpolation);
+            }
+            Err(err) => println!("Could not seek to {:.3}: {}", time, err),
+        }
+    }
+
+    /// The media position queried now minus the time of the game clock.
+    pub fn drift(&self) -> Option<f64> {
+        let time = self.time();
+        self.position().map(|position| position - time)
+    }
+
+    fn position(&self) -> Option<f64> {
+        self.playbin
+            .query_position::<gstreamer::ClockTime>()
+            .and_then(|position| position.nseconds())
+            .map(seconds)
+    }
+
+    pub fn playing(&self) -> bool {
+        let (_, state, _) = self.playbin.get_state(gstreamer::ClockTime::from_nseconds(0));
+        state == gstreamer::State::Playing
+    }
+
+    fn pipeline_time(&self) -> Option<f64> {
+        self.playbin
+            .get_clock()
+            .and_then(|clock| clock.get_time().nseconds())
+            .map(seconds)
+    }
+}
+
+impl GameClock for PipelineClock {
+    fn time(&self) -> f64 {
+        let now = match self.pipeline_time() {
+            Some(now) => now,
+            // No clock before the pipeline is playing
+            None => return self.position().unwrap_or(0.0),
+        };
+        let mut interpolation = self.interpolation.get();
+        let time = interpolation.time(now, self.playing(), || self.position().unwrap_or(0.0));
+        self.interpolation.set(interpolation);
+        time
+    }
+
+    fn rate(&self) -> f64 {
+        self.interpolation.get().rate()
+    }
+
+    // The media did not move while paused
+    fn time_at(&self, instant: Instant) -> f64 {
+        let elapsed = Instant::now().saturating_duration_since(instant).as_secs_f64();
+        self.time() - self.interpolation.get().media_seconds(elapsed, self.playing())
+    }
+}
+
+fn seconds(nanoseconds: u64) -> f64 {
+    nanoseconds as f64 / 1_000_000_000.0
+}
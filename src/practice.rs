@@ -0,0 +1,30 @@
+// Practice at a reduced rate: the media plays slower and the play is marked
+// with its rate as a mod, so that it is not taken for a play at full speed.
+// Everything timed in media seconds is scaled by the rate to stay the same
+// in real seconds: the judgement windows, the offsets of the profile and the
+// scroll speed.
+
+/// Slowest and fastest practice rates, as fractions of the normal speed.
+pub const MIN_RATE: f64 = 0.5;
+pub const MAX_RATE: f64 = 0.9;
+
+/// Whether `rate` is one to practice at, or the normal speed. Rates are in
+/// hundredths, so that the mod names the exact rate played at.
+pub fn valid_rate(rate: f64) -> bool {
+    rate == 1.0 || ((MIN_RATE..=MAX_RATE).contains(&rate) && mod_rate(&mod_name(rate)) == Some(rate))
+}
+
+/// The mod a play at `rate` is marked with, "0.75x".
+pub fn mod_name(rate: f64) -> String {
+    format!("{:.2}x", rate)
+}
+
+/// The rate of a mod named by `mod_name`, `None` for other mods.
+pub fn mod_rate(name: &str) -> Option<f64> {
+    name.strip_suffix('x')?.parse::<f64>().ok().filter(|rate| *rate > 0.0)
+}
+
+/// The rate a play with `mods` was played at, 1 at the normal speed.
+pub fn rate(mods: &[String]) -> f64 {
+    mods.iter().find_map(|name| mod_rate(name)).unwrap_or(1.0)
+}
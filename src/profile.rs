@@ -0,0 +1,243 @@
+// Player profiles: the settings of everyone playing on the machine, kept in
+// one text file with a section per profile:
+//
+//     last=ana
+//     [ana]
+//     audio_offset=0.012
+//     visual_offset=0.000
+//     scroll_speed=300
+//     theme=dark
+//     bind=j a
+//     bind=k space
+//
+// A `bind` line makes the first key play the notes of the second one.
+// Scores are kept in the score database under the profile name.
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::chart::{key_from_native, key_to_native};
+
+/// Profile played with when none was ever picked.
+pub const DEFAULT_PROFILE: &str = "player";
+/// Pixels per second the notes scroll at by default.
+pub const DEFAULT_SCROLL_SPEED: f64 = 300.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Theme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 2] = [Theme::Dark, Theme::Light];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Theme> {
+        Theme::ALL.iter().cloned().find(|theme| theme.name() == name)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    /// Key pressed and the chart key it plays.
+    pub bindings: Vec<(char, char)>,
+    /// Seconds the sound comes out late, taken off the time of key presses.
+    pub audio_offset: f64,
+    /// Seconds the picture comes out late, notes are drawn that much ahead.
+    pub visual_offset: f64,
+    /// Pixels per second.
+    pub scroll_speed: f64,
+    pub theme: Theme,
+}
+
+impl Profile {
+    pub fn new(name: &str) -> Profile {
+        Profile {
+            name: name.to_string(),
+            bindings: Vec::new(),
+            audio_offset: 0.0,
+            visual_offset: 0.0,
+            scroll_speed: DEFAULT_SCROLL_SPEED,
+            theme: Theme::Dark,
+        }
+    }
+
+    /// Chart key played by pressing `key`.
+    pub fn key(&self, key: char) -> char {
+        self.bindings
+            .iter()
+            .find(|(pressed, _)| *pressed == key)
+            .map_or(key, |(_, played)| *played)
+    }
+
+    /// Make `pressed` play `played`, replacing its previous binding.
+    pub fn bind(&mut self, pressed: char, played: char) {
+        self.bindings.retain(|(key, _)| *key != pressed);
+        if pressed != played {
+            self.bindings.push((pressed, played));
+        }
+    }
+
+    /// Change the setting `key` as it is written in the profiles file.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let number = || -> Result<f64> {
+            let number: f64 = value.parse().with_context(|| format!("invalid number {}", value))?;
+            if !number.is_finite() {
+                bail!("invalid number {}", value);
+            }
+            Ok(number)
+        };
+        match key {
+            "audio_offset" => self.audio_offset = number()?,
+            "visual_offset" => self.visual_offset = number()?,
+            "scroll_speed" => {
+                let speed = number()?;
+                if speed <= 0.0 {
+                    bail!("invalid scroll speed {}", value);
+                }
+                self.scroll_speed = speed;
+            }
+            "theme" => self.theme = Theme::from_name(value).ok_or_else(|| anyhow!("unknown theme {}", value))?,
+            "bind" => {
+                let mut fields = value.split_whitespace();
+                let mut key = || {
+                    let field = fields.next().unwrap_or("");
+                    key_from_native(field).ok_or_else(|| anyhow!("invalid key {}", field))
+                };
+                let pressed = key()?;
+                let played = key()?;
+                self.bind(pressed, played);
+            }
+            key => bail!("unknown setting {}", key),
+        }
+        Ok(())
+    }
+
+    /// The section of the profile in the profiles file.
+    pub fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("[{}]", self.name),
+            format!("audio_offset={:.3}", self.audio_offset),
+            format!("visual_offset={:.3}", self.visual_offset),
+            format!("scroll_speed={}", self.scroll_speed),
+            format!("theme={}", self.theme.name()),
+        ];
+        for (pressed, played) in &self.bindings {
+            lines.push(format!("bind={} {}", key_to_native(*pressed), key_to_native(*played)));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Whether `name` can be the name of a profile.
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.trim() == name
+        && !name.chars().any(|character| character.is_control() || character == '[' || character == ']')
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Profiles {
+    pub profiles: Vec<Profile>,
+    /// Name of the profile used last.
+    pub last: Option<String>,
+}
+
+impl Profiles {
+    /// Read the profiles at `path`, none when the file does not exist yet.
+    pub fn load(path: &Path) -> Result<Profiles> {
+        if !path.exists() {
+            return Ok(Profiles::default());
+        }
+        let contents = fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        Profiles::parse(&contents).with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.to_text()).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    pub fn parse(contents: &str) -> Result<Profiles> {
+        let mut profiles = Profiles::default();
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let parsed = if line.starts_with('[') && line.ends_with(']') {
+                let name = &line[1..line.len() - 1];
+                if profiles.get(name).is_some() {
+                    Err(anyhow!("profile {} is there twice", name))
+                } else {
+                    profiles.profiles.push(Profile::new(name));
+                    Ok(())
+                }
+            } else {
+                let mut fields = line.splitn(2, '=');
+                let key = fields.next().unwrap_or("").trim();
+                let value = fields.next().unwrap_or("").trim();
+                match profiles.profiles.last_mut() {
+                    Some(profile) => profile.set(key, value),
+                    None if key == "last" => {
+                        profiles.last = Some(value.to_string()).filter(|name| !name.is_empty());
+                        Ok(())
+                    }
+                    None => Err(anyhow!("setting outside of a profile")),
+                }
+            };
+            parsed.with_context(|| format!("line {}", number + 1))?;
+        }
+        Ok(profiles)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut lines = Vec::new();
+        if let Some(ref last) = self.last {
+            lines.push(format!("last={}", last));
+        }
+        for profile in &self.profiles {
+            lines.push(profile.to_text());
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Profile used last, if it still exists.
+    pub fn last_profile(&self) -> Option<&Profile> {
+        self.last.as_ref().and_then(|name| self.get(name))
+    }
+
+    /// Play with the profile `name`, created with the default settings if
+    /// there is none yet, and remember it as the last one used.
+    pub fn select(&mut self, name: &str) -> Result<&Profile> {
+        if !valid_name(name) {
+            bail!("invalid profile name {:?}", name);
+        }
+        if self.get(name).is_none() {
+            self.profiles.push(Profile::new(name));
+        }
+        self.last = Some(name.to_string());
+        Ok(self.get(name).unwrap())
+    }
+
+    /// Replace the stored settings of `profile`, adding it if it is new.
+    pub fn update(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|stored| stored.name == profile.name) {
+            Some(stored) => *stored = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+}
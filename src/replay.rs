@@ -0,0 +1,327 @@
+// Replays: every key pressed and released during a play, with the media time
+// it happened at, and what is needed to play it again the same way.
+//
+// The file is binary and small, a few bytes per input:
+//
+//     "MRRP", version byte
+//     chart hash (u64), title, difficulty
+//     judgement windows (3 x f64), mods (count, names)
+//     claimed score: points, count of each judgement, combo, max combo
+//     input count, then per input the change of time in microseconds since
+//     the previous input and the key with the press or release in its low bit
+//
+// Integers are LEB128 varints, times zigzag encoded varints, strings a
+// varint length and UTF-8.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::chart::Chart;
+use crate::gameplay::Gameplay;
+use crate::judgement::Windows;
+use crate::score::Score;
+
+pub const REPLAY_EXTENSION: &str = "mrreplay";
+
+const MAGIC: &[u8; 4] = b"MRRP";
+const VERSION: u8 = 1;
+const MICROSECONDS: f64 = 1_000_000.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum KeyAction {
+    Down,
+    Up,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Input {
+    /// Media time in seconds, to the microsecond.
+    pub time: f64,
+    pub key: char,
+    pub action: KeyAction,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replay {
+    pub chart_hash: u64,
+    pub title: String,
+    pub difficulty: String,
+    pub windows: Windows,
+    pub mods: Vec<String>,
+    /// Score reached when the replay was recorded.
+    pub score: Score,
+    pub inputs: Vec<Input>,
+}
+
+/// Media time rounded to what a replay keeps, inputs are judged at this time
+/// so that replaying them gives the same judgements.
+pub fn quantize(time: f64) -> f64 {
+    (time * MICROSECONDS).round() / MICROSECONDS
+}
+
+impl Replay {
+    pub fn new(chart: &Chart, windows: Windows, mods: Vec<String>, score: Score, inputs: Vec<Input>) -> Replay {
+        Replay {
+            chart_hash: chart.hash(),
+            title: chart.metadata.title.clone(),
+            difficulty: chart.metadata.difficulty.clone(),
+            windows,
+            mods,
+            score,
+            inputs,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Replay> {
+        let bytes = fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+        Replay::decode(&bytes).with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, self.encode()).with_context(|| format!("Could not write {}", path.display()))
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.bytes.push(VERSION);
+        writer.bytes.extend_from_slice(&self.chart_hash.to_le_bytes());
+        writer.string(&self.title);
+        writer.string(&self.difficulty);
+        for window in &[self.windows.perfect, self.windows.great, self.windows.good] {
+            writer.bytes.extend_from_slice(&window.to_le_bytes());
+        }
+        writer.varint(self.mods.len() as u64);
+        for name in &self.mods {
+            writer.string(name);
+        }
+        writer.varint(self.score.points);
+        for count in &self.score.counts {
+            writer.varint(*count as u64);
+        }
+        writer.varint(self.score.combo as u64);
+        writer.varint(self.score.max_combo as u64);
+
+        writer.varint(self.inputs.len() as u64);
+        let mut previous = 0;
+        for input in &self.inputs {
+            let time = (input.time * MICROSECONDS).round() as i64;
+            writer.signed(time - previous);
+            previous = time;
+            let up = match input.action {
+                KeyAction::Down => 0,
+                KeyAction::Up => 1,
+            };
+            writer.varint((input.key as u64) << 1 | up);
+        }
+        writer.bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Replay> {
+        let mut reader = Reader { bytes, position: 0 };
+        if reader.take(4)? != MAGIC {
+            bail!("not a replay file");
+        }
+        let version = reader.take(1)?[0];
+        if version != VERSION {
+            bail!("unsupported replay version {}", version);
+        }
+        let chart_hash = reader.u64()?;
+        let title = reader.string()?;
+        let difficulty = reader.string()?;
+        let windows = Windows {
+            perfect: reader.f64()?,
+            great: reader.f64()?,
+            good: reader.f64()?,
+        };
+        let mods = (0..reader.varint()?)
+            .map(|_| reader.string())
+            .collect::<Result<Vec<String>>>()?;
+        let mut score = Score::new();
+        score.points = reader.varint()?;
+        for count in &mut score.counts {
+            *count = reader.varint()? as usize;
+        }
+        score.combo = reader.varint()? as usize;
+        score.max_combo = reader.varint()? as usize;
+
+        let count = reader.varint()?;
+        let mut inputs = Vec::new();
+        let mut time = 0;
+        for _ in 0..count {
+            time += reader.signed()?;
+            let field = reader.varint()?;
+            let key = std::char::from_u32((field >> 1) as u32)
+                .ok_or_else(|| anyhow!("invalid key at byte {}", reader.position))?;
+            let action = if field & 1 == 0 { KeyAction::Down } else { KeyAction::Up };
+            inputs.push(Input {
+                time: time as f64 / MICROSECONDS,
+                key,
+                action,
+            });
+        }
+        Ok(Replay {
+            chart_hash,
+            title,
+            difficulty,
+            windows,
+            mods,
+            score,
+            inputs,
+        })
+    }
+
+    /// Play the inputs on `chart` from the start to after its last note.
+    pub fn play(&self, chart: &Chart) -> Gameplay {
+        let mut playback = Playback::new(self.clone());
+        let mut gameplay = playback.start(chart.clone());
+        let end = chart.notes.iter().map(|note| note.end_time()).fold(0.0, f64::max);
+        playback.seek(&mut gameplay, end + self.windows.good + 1.0);
+        gameplay
+    }
+
+    /// Play the replay again on `chart` and check that it gives the score it
+    /// claims, which is returned.
+    pub fn verify(&self, chart: &Chart) -> Result<Score> {
+        if chart.hash() != self.chart_hash {
+            bail!("the replay was not recorded on this chart");
+        }
+        let score = self.play(chart).score;
+        if score != self.score {
+            bail!(
+                "the replay claims {} points, {} max combo and {:?} judgements but gives {} points, {} max combo and {:?}",
+                self.score.points, self.score.max_combo, self.score.counts,
+                score.points, score.max_combo, score.counts
+            );
+        }
+        Ok(score)
+    }
+}
+
+/// Plays the inputs of a replay into a gameplay as the media time goes.
+#[derive(Clone, Debug)]
+pub struct Playback {
+    pub replay: Replay,
+    // Next input to play, and media time played up to
+    next: usize,
+    time: f64,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Playback {
+        Playback {
+            replay,
+            next: 0,
+            time: 0.0,
+        }
+    }
+
+    /// Gameplay of `chart` with the settings of the replay, before any input.
+    pub fn start(&self, chart: Chart) -> Gameplay {
+        let mut gameplay = Gameplay::new(Some(chart));
+        gameplay.windows = self.replay.windows;
+        gameplay.mods = self.replay.mods.clone();
+        gameplay
+    }
+
+    /// Play the inputs up to `time`. Going back in time plays the replay
+    /// again from the start.
+    pub fn seek(&mut self, gameplay: &mut Gameplay, time: f64) {
+        if time < self.time {
+            *gameplay = self.start(gameplay.chart.take().unwrap_or_default());
+            self.next = 0;
+        }
+        while let Some(input) = self.replay.inputs.get(self.next).filter(|input| input.time <= time) {
+            gameplay.input(input);
+            self.next += 1;
+        }
+        gameplay.update(time);
+        self.time = time;
+    }
+}
+
+/// Where a replay of `title` recorded at `timestamp` (Unix seconds) is saved
+/// in `directory`.
+pub fn replay_path(directory: &Path, title: &str, timestamp: u64) -> PathBuf {
+    let title: String = title
+        .chars()
+        .filter(|character| character.is_alphanumeric() || " -_".contains(*character))
+        .collect();
+    let title = if title.trim().is_empty() { "replay" } else { title.trim() };
+    directory.join(format!("{} {}.{}", title, timestamp, REPLAY_EXTENSION))
+}
+
+#[derive(Default)]
+struct Writer {
+    bytes: Vec<u8>,
+}
+
+impl Writer {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn signed(&mut self, value: i64) {
+        self.varint(((value << 1) ^ (value >> 63)) as u64);
+    }
+
+    fn string(&mut self, text: &str) {
+        self.varint(text.len() as u64);
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8]> {
+        let end = self.position + length;
+        if end > self.bytes.len() {
+            bail!("replay ends too early");
+        }
+        let bytes = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    fn f64(&mut self) -> Result<f64> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+
+    fn varint(&mut self) -> Result<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        bail!("invalid number at byte {}", self.position)
+    }
+
+    fn signed(&mut self) -> Result<i64> {
+        let value = self.varint()?;
+        Ok((value >> 1) as i64 ^ -((value & 1) as i64))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let length = self.varint()? as usize;
+        let bytes = self.take(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| anyhow!("invalid text at byte {}", self.position))
+    }
+}
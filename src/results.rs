@@ -0,0 +1,98 @@
+// What the results screen shows about a finished play: the judgement
+// breakdown, how the hits were spread around their targets, and how the
+// accuracy went along the song.
+use crate::gameplay::Gameplay;
+use crate::score::Score;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Results {
+    pub score: Score,
+    pub accuracy: f64,
+    pub grade: &'static str,
+    /// Hits per offset bin, from `-windows.good` (early) to `windows.good`.
+    pub histogram: Vec<usize>,
+    /// Mean hit offset in seconds, negative when early on average.
+    pub mean_offset: Option<f64>,
+    /// Ten times the standard deviation of the hit offsets in milliseconds,
+    /// lower is steadier.
+    pub unstable_rate: Option<f64>,
+    /// Accuracy of each equal part of the chart timeline, `None` for parts
+    /// without notes.
+    pub sections: Vec<Option<f64>>,
+}
+
+/// Grade letter for an accuracy between 0 and 1.
+pub fn grade(accuracy: f64) -> &'static str {
+    match accuracy {
+        accuracy if accuracy >= 1.0 => "SS",
+        accuracy if accuracy >= 0.95 => "S",
+        accuracy if accuracy >= 0.9 => "A",
+        accuracy if accuracy >= 0.8 => "B",
+        accuracy if accuracy >= 0.7 => "C",
+        _ => "D",
+    }
+}
+
+impl Results {
+    /// Results of `gameplay`, with `bins` histogram bins and the chart split
+    /// in `sections` parts.
+    pub fn new(gameplay: &Gameplay, bins: usize, sections: usize) -> Results {
+        // In real time, when practicing at a reduced rate
+        let rate = gameplay.rate();
+        let offsets: Vec<f64> = gameplay
+            .judgements()
+            .iter()
+            .filter_map(|judged| judged.offset)
+            .map(|offset| offset / rate)
+            .collect();
+        let good = gameplay.windows.good / rate;
+
+        let mut histogram = vec![0; bins];
+        for offset in &offsets {
+            let bin = ((offset + good) / (2.0 * good) * bins as f64).floor().max(0.0) as usize;
+            if let Some(count) = histogram.get_mut(bin.min(bins.saturating_sub(1))) {
+                *count += 1;
+            }
+        }
+
+        let mean_offset = if offsets.is_empty() {
+            None
+        } else {
+            Some(offsets.iter().sum::<f64>() / offsets.len() as f64)
+        };
+        let unstable_rate = mean_offset.map(|mean| {
+            let variance = offsets.iter().map(|offset| (offset - mean).powi(2)).sum::<f64>() / offsets.len() as f64;
+            variance.sqrt() * 1000.0 * 10.0
+        });
+
+        // Weight sum and count of the judgements of each section, by the time
+        // of their note
+        let mut totals = vec![(0.0, 0); sections];
+        if let Some(ref chart) = gameplay.chart {
+            let end = chart.notes.iter().map(|note| note.end_time()).fold(0.0, f64::max);
+            for judged in gameplay.judgements() {
+                let time = chart.notes[judged.note].time;
+                let section = if end > 0.0 { (time / end * sections as f64) as usize } else { 0 };
+                if let Some(total) = totals.get_mut(section.min(sections.saturating_sub(1))) {
+                    total.0 += judged.judgement.weight();
+                    total.1 += 1;
+                }
+            }
+        }
+        let sections = totals
+            .iter()
+            .map(|&(weight, count)| if count == 0 { None } else { Some(weight / count as f64) })
+            .collect();
+
+        let accuracy = gameplay.score.accuracy();
+        Results {
+            score: gameplay.score.clone(),
+            accuracy,
+            grade: grade(accuracy),
+            histogram,
+            mean_offset,
+            unstable_rate,
+            sections,
+        }
+    }
+}
@@ -0,0 +1,156 @@
+// When the event loop of the player wakes up. Outside of animations the UI is
+// set at most every 16ms and only when events arrive; while animating, ticks
+// come at a fixed rate and frames as often as the config allows. Every time
+// is given by the caller, so that the schedule can be checked without a
+// window.
+use std::time::{Duration, Instant};
+
+/// Ticks run at most for one frame, so that a long stall does not make the
+/// loop spend the next frames catching up.
+pub const MAX_TICKS_PER_FRAME: u32 = 16;
+/// Time between two UI updates outside of animations.
+pub const UI_INTERVAL: Duration = Duration::from_millis(16);
+
+/// How often the loop wakes up while animating.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LoopConfig {
+    /// Simulation ticks per second.
+    pub tick_rate: f64,
+    /// Frames per second at most, `None` to draw as fast as possible.
+    pub max_fps: Option<f64>,
+}
+
+impl Default for LoopConfig {
+    fn default() -> LoopConfig {
+        LoopConfig {
+            tick_rate: 240.0,
+            max_fps: Some(60.0),
+        }
+    }
+}
+
+/// What the loop does until its next event.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WakeUp {
+    /// Sleep until an event arrives.
+    Wait,
+    /// Sleep until an event arrives or the instant is reached.
+    At(Instant),
+    /// Come back right away.
+    Poll,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Schedule {
+    tick_step: Duration,
+    frame_step: Option<Duration>,
+    animating: bool,
+    // When the UI is set next outside of animations, `None` to wait for
+    // events
+    next_update: Option<Instant>,
+    // Real time not simulated yet, and when it was last counted
+    unticked: Duration,
+    last_tick: Instant,
+    next_frame: Instant,
+}
+
+impl Schedule {
+    pub fn new(config: LoopConfig, now: Instant) -> Schedule {
+        Schedule {
+            tick_step: Duration::from_secs_f64(1.0 / config.tick_rate),
+            frame_step: config.max_fps.map(|fps| Duration::from_secs_f64(1.0 / fps)),
+            animating: false,
+            next_update: None,
+            unticked: Duration::from_secs(0),
+            last_tick: now,
+            next_frame: now,
+        }
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.animating
+    }
+
+    /// Whether the loop only wakes up for events.
+    pub fn is_waiting(&self) -> bool {
+        !self.animating && self.next_update.is_none()
+    }
+
+    /// Real time simulated by one tick.
+    pub fn tick_step(&self) -> Duration {
+        self.tick_step
+    }
+
+    /// How many ticks to run at `now` while animating. Real time past
+    /// `MAX_TICKS_PER_FRAME` ticks is dropped.
+    pub fn ticks(&mut self, now: Instant) -> u32 {
+        self.unticked += now.saturating_duration_since(self.last_tick);
+        self.last_tick = now;
+        let mut ticks = 0;
+        while self.unticked >= self.tick_step && ticks < MAX_TICKS_PER_FRAME {
+            self.unticked -= self.tick_step;
+            ticks += 1;
+        }
+        if ticks == MAX_TICKS_PER_FRAME {
+            self.unticked = Duration::from_secs(0);
+        }
+        ticks
+    }
+
+    /// Whether a frame is due at `now` while animating. Frames keep to their
+    /// step rather than to when they were drawn, a frame that fell behind
+    /// makes the next one due right away.
+    pub fn frame_due(&mut self, now: Instant) -> bool {
+        if now < self.next_frame {
+            return false;
+        }
+        self.next_frame = self.frame_step.map_or(now, |step| (self.next_frame + step).max(now));
+        true
+    }
+
+    /// The UI was set at `now` and answered `needs_redraw` and `animating`.
+    /// Returns whether to redraw: always while animating, and otherwise only
+    /// when asked to.
+    pub fn ui_set(&mut self, now: Instant, needs_redraw: bool, animating: bool) -> bool {
+        if self.animating {
+            self.animating = animating;
+            // Set the UI once more when the animation stops
+            if !animating {
+                self.next_update = Some(now);
+            }
+            return true;
+        }
+        self.animating = animating;
+        if animating {
+            // Start ticking from now on
+            self.last_tick = now;
+            self.unticked = Duration::from_secs(0);
+            self.next_frame = now;
+            self.next_update = None;
+            return true;
+        }
+        if needs_redraw {
+            self.next_update = Some(now + UI_INTERVAL);
+            true
+        } else {
+            // No need to redraw until more events arrive
+            self.next_update = None;
+            false
+        }
+    }
+
+    /// When the loop wakes up next: for the next tick or frame while
+    /// animating, for the next UI update or an event otherwise.
+    pub fn wake_up(&self) -> WakeUp {
+        if self.animating {
+            return match self.frame_step {
+                Some(_) => WakeUp::At(self.next_frame.min(self.last_tick + self.tick_step)),
+                None => WakeUp::Poll,
+            };
+        }
+        match self.next_update {
+            Some(next_update) => WakeUp::At(next_update),
+            None => WakeUp::Wait,
+        }
+    }
+}
@@ -0,0 +1,70 @@
+// Running score of a play: points, how many of each judgement, and combo.
+use crate::judgement::Judgement;
+use crate::typing::{PhraseResult, CHARACTER_POINTS};
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Score {
+    pub points: u64,
+    /// Count of each judgement, in the order of `Judgement::ALL`.
+    pub counts: [usize; 4],
+    /// Judgements in a row that were not a miss.
+    pub combo: usize,
+    pub max_combo: usize,
+}
+
+impl Score {
+    pub fn new() -> Score {
+        Score::default()
+    }
+
+    pub fn judge(&mut self, judgement: Judgement) {
+        self.counts[index_of(judgement)] += 1;
+        if judgement == Judgement::Miss {
+            self.combo = 0;
+        } else {
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        }
+    }
+
+    /// Add the judgement of a key or hold note, worth as many points as a
+    /// character.
+    pub fn add_key(&mut self, judgement: Judgement) {
+        self.points += (CHARACTER_POINTS * judgement.weight()).round() as u64;
+        self.judge(judgement);
+    }
+
+    /// Add a finished phrase: its points, and each character as a judgement.
+    pub fn add_phrase(&mut self, result: &PhraseResult) {
+        self.points += result.score();
+        for judgement in &result.judgements {
+            self.judge(*judgement);
+        }
+    }
+
+    pub fn count(&self, judgement: Judgement) -> usize {
+        self.counts[index_of(judgement)]
+    }
+
+    /// Number of judgements given so far.
+    pub fn judged(&self) -> usize {
+        self.counts.iter().sum()
+    }
+
+    /// Mean judgement weight, 1.0 before anything was judged.
+    pub fn accuracy(&self) -> f64 {
+        let judged = self.judged();
+        if judged == 0 {
+            return 1.0;
+        }
+        let total: f64 = Judgement::ALL
+            .iter()
+            .map(|judgement| judgement.weight() * self.count(*judgement) as f64)
+            .sum();
+        total / judged as f64
+    }
+}
+
+fn index_of(judgement: Judgement) -> usize {
+    Judgement::ALL.iter().position(|j| *j == judgement).unwrap()
+}
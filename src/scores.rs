@@ -0,0 +1,238 @@
+// Local high scores: every play finished, for every chart and profile, kept
+// in one text file. The first line holds the schema version, then each line
+// is one play with its fields separated by tabs:
+//
+//     mechanical scores 1
+//     chart hash  title  difficulty  profile  points  accuracy
+//     perfect,great,good,miss  max combo  mods  date  replay
+//
+// New plays are appended, and lines that can't be read are skipped and
+// counted instead of failing the whole file.
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+use crate::chart::Chart;
+use crate::score::Score;
+
+pub const SCHEMA_VERSION: u32 = 1;
+const HEADER: &str = "mechanical scores";
+const FIELDS: usize = 11;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Entry {
+    pub chart_hash: u64,
+    pub title: String,
+    pub difficulty: String,
+    pub profile: String,
+    pub score: Score,
+    pub accuracy: f64,
+    pub mods: Vec<String>,
+    /// Unix seconds at the end of the play.
+    pub date: u64,
+    pub replay: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ScoreDatabase {
+    path: PathBuf,
+    pub entries: Vec<Entry>,
+    /// Lines that could not be read.
+    pub skipped: usize,
+}
+
+impl ScoreDatabase {
+    /// Read the database at `path`, empty when the file does not exist yet.
+    pub fn open(path: &Path) -> Result<ScoreDatabase> {
+        let mut database = ScoreDatabase {
+            path: path.to_path_buf(),
+            entries: Vec::new(),
+            skipped: 0,
+        };
+        if !path.exists() {
+            return Ok(database);
+        }
+        let contents = fs::read_to_string(path).with_context(|| format!("Could not read {}", path.display()))?;
+        let mut lines = contents.lines();
+        let version = lines
+            .next()
+            .and_then(|header| header.strip_prefix(HEADER))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or_else(|| anyhow!("{} is not a score database", path.display()))?;
+        if version > SCHEMA_VERSION {
+            bail!("{} was written by a newer version (schema {})", path.display(), version);
+        }
+        for line in lines.filter(|line| !line.trim().is_empty()) {
+            match Entry::parse(line) {
+                Ok(entry) => database.entries.push(entry),
+                Err(_) => database.skipped += 1,
+            }
+        }
+        Ok(database)
+    }
+
+    /// Add a play, and append it to the file.
+    pub fn add(&mut self, entry: Entry) -> Result<()> {
+        let new = fs::metadata(&self.path).map(|metadata| metadata.len() == 0).unwrap_or(true);
+        let mut file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("Could not open {}", self.path.display()))?;
+        let mut text = String::new();
+        if new {
+            text += &format!("{} {}\n", HEADER, SCHEMA_VERSION);
+        } else if !ends_with_newline(&mut file)? {
+            // A play cut short by a crash must not swallow this one
+            text.push('\n');
+        }
+        text += &entry.to_line();
+        text.push('\n');
+        file.write_all(text.as_bytes())
+            .with_context(|| format!("Could not write {}", self.path.display()))?;
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    /// Best plays of a chart, of one profile or of everyone, best first.
+    /// Ties go to the earliest play.
+    pub fn top(&self, chart_hash: u64, profile: Option<&str>, count: usize) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.chart_hash == chart_hash)
+            .filter(|entry| profile.map(|profile| entry.profile == profile).unwrap_or(true))
+            .collect();
+        entries.sort_by(|a, b| b.score.points.cmp(&a.score.points).then(a.date.cmp(&b.date)));
+        entries.truncate(count);
+        entries
+    }
+
+    /// Every play of `profile`, in the order they were played.
+    pub fn history(&self, profile: &str) -> Vec<&Entry> {
+        self.entries.iter().filter(|entry| entry.profile == profile).collect()
+    }
+
+    pub fn personal_best(&self, chart_hash: u64, profile: &str) -> Option<&Entry> {
+        self.top(chart_hash, Some(profile), 1).into_iter().next()
+    }
+}
+
+impl Entry {
+    pub fn new(chart: &Chart, profile: &str, score: Score, mods: Vec<String>, date: u64, replay: Option<PathBuf>) -> Entry {
+        Entry {
+            chart_hash: chart.hash(),
+            title: chart.metadata.title.clone(),
+            difficulty: chart.metadata.difficulty.clone(),
+            profile: profile.to_string(),
+            accuracy: score.accuracy(),
+            score,
+            mods,
+            date,
+            replay,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        let counts: Vec<String> = self.score.counts.iter().map(|count| count.to_string()).collect();
+        let mods: Vec<String> = self.mods.iter().map(|name| escape(name).replace(',', "\\,")).collect();
+        let fields = [
+            format!("{:016x}", self.chart_hash),
+            escape(&self.title),
+            escape(&self.difficulty),
+            escape(&self.profile),
+            self.score.points.to_string(),
+            format!("{:.6}", self.accuracy),
+            counts.join(","),
+            self.score.max_combo.to_string(),
+            mods.join(","),
+            self.date.to_string(),
+            self.replay.as_ref().map(|path| escape(&path.to_string_lossy())).unwrap_or_default(),
+        ];
+        fields.join("\t")
+    }
+
+    fn parse(line: &str) -> Result<Entry> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != FIELDS {
+            bail!("{} fields instead of {}", fields.len(), FIELDS);
+        }
+        let mut score = Score::new();
+        score.points = fields[4].parse()?;
+        let counts = fields[6]
+            .split(',')
+            .map(|count| count.parse::<usize>())
+            .collect::<std::result::Result<Vec<usize>, _>>()?;
+        if counts.len() != score.counts.len() {
+            bail!("{} judgement counts", counts.len());
+        }
+        score.counts.copy_from_slice(&counts);
+        score.max_combo = fields[7].parse()?;
+        Ok(Entry {
+            chart_hash: u64::from_str_radix(fields[0], 16)?,
+            title: unescape(fields[1]),
+            difficulty: unescape(fields[2]),
+            profile: unescape(fields[3]),
+            score,
+            accuracy: fields[5].parse()?,
+            mods: split_list(fields[8]).iter().map(|name| unescape(name)).collect(),
+            date: fields[9].parse()?,
+            replay: Some(fields[10]).filter(|path| !path.is_empty()).map(|path| PathBuf::from(unescape(path))),
+        })
+    }
+}
+
+fn ends_with_newline(file: &mut fs::File) -> Result<bool> {
+    let mut last = [0];
+    file.seek(SeekFrom::End(-1))
+        .and_then(|_| file.read_exact(&mut last))
+        .context("Could not read the end of the score database")?;
+    Ok(last[0] == b'\n')
+}
+
+// Tabs and line breaks would split the fields and lines
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+// Items of a comma separated field, still escaped
+fn split_list(field: &str) -> Vec<&str> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, character) in field.char_indices() {
+        match character {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => {
+                items.push(&field[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&field[start..]);
+    items.retain(|item| !item.is_empty());
+    items
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut characters = text.chars();
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            unescaped.push(character);
+            continue;
+        }
+        match characters.next() {
+            Some('t') => unescaped.push('\t'),
+            Some('n') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
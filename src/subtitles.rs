@@ -0,0 +1,93 @@
+// Subtitles embedded in the media, captured from the playbin text streams
+// through an appsink and turned into phrase notes while the media plays.
+use std::sync::{Arc, Mutex};
+
+use anyhow::{anyhow, Result};
+use gstreamer::prelude::*;
+
+use mechanical::chart::{Note, Phrase};
+use mechanical::lyrics::{self, Line};
+
+/// How long a cue lasts when its buffer has no duration, in seconds.
+const DEFAULT_CUE_LENGTH: f64 = 3.0;
+
+pub struct SubtitleCapture {
+    // Cues received by the streaming thread and not yet added to a chart
+    cues: Arc<Mutex<Vec<Line>>>,
+}
+
+impl SubtitleCapture {
+    /// Replace the playbin text sink with an appsink collecting the cues.
+    /// Has to be called before the playbin leaves the `Null` state.
+    pub fn attach(playbin: &gstreamer::Element) -> Result<SubtitleCapture> {
+        let sink = gstreamer::ElementFactory::make("appsink", Some("subtitle_sink"))?;
+        // Get the cues as soon as they are demuxed instead of at their time,
+        // so the notes are in the chart before they have to be typed
+        sink.set_property("sync", &false)?;
+        let appsink = sink
+            .clone()
+            .dynamic_cast::<gstreamer_app::AppSink>()
+            .map_err(|_| anyhow!("appsink is not an AppSink"))?;
+        appsink.set_caps(Some(&gstreamer::Caps::new_simple(
+            "text/x-raw",
+            &[("format", &"utf8")],
+        )));
+
+        let cues = Arc::new(Mutex::new(Vec::new()));
+        let sink_cues = Arc::clone(&cues);
+        appsink.set_callbacks(
+            gstreamer_app::AppSinkCallbacks::builder()
+                .new_sample(move |appsink| {
+                    let sample = appsink.pull_sample().map_err(|_| gstreamer::FlowError::Eos)?;
+                    if let Some(cue) = sample_to_line(&sample) {
+                        sink_cues.lock().unwrap().push(cue);
+                    }
+                    Ok(gstreamer::FlowSuccess::Ok)
+                })
+                .build(),
+        );
+
+        playbin.set_property("text-sink", &sink)?;
+        Ok(SubtitleCapture { cues })
+    }
+
+    /// Take the cues received since the last call.
+    pub fn drain(&self) -> Vec<Line> {
+        self.cues.lock().unwrap().drain(..).collect()
+    }
+}
+
+fn sample_to_line(sample: &gstreamer::Sample) -> Option<Line> {
+    let buffer = sample.get_buffer()?;
+    let map = buffer.map_readable().ok()?;
+    let text = lyrics::strip_markup(&String::from_utf8_lossy(&map));
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.is_empty() {
+        return None;
+    }
+
+    // Buffer timestamps are running time, the chart uses the media position
+    let mut pts = buffer.get_pts();
+    if let Some(segment) = sample.get_segment() {
+        if let Some(segment) = segment.downcast_ref::<gstreamer::ClockTime>() {
+            pts = segment.to_stream_time(pts);
+        }
+    }
+    let start = pts.nseconds()? as f64 / 1_000_000_000.0;
+    let end = buffer
+        .get_duration()
+        .nseconds()
+        .map(|duration| start + duration as f64 / 1_000_000_000.0)
+        .unwrap_or(start + DEFAULT_CUE_LENGTH);
+    Some(Line {
+        start,
+        end,
+        text,
+        words: Vec::new(),
+    })
+}
+
+/// The cue as a phrase note to type within its display time.
+pub fn to_note(cue: &Line) -> Note {
+    Note::phrase(cue.start, Phrase::window(&cue.text, cue.end))
+}
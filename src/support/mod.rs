@@ -1,18 +1,31 @@
 #![allow(dead_code)]
 
+use std::time::{Duration, Instant};
+
 use glium::{
     glutin::{event, event_loop},
     Display,
 };
 
+use mechanical::schedule::{Schedule, WakeUp};
+
+pub use mechanical::schedule::LoopConfig;
+
 pub enum Request<'a, 'b: 'a> {
     Event {
         event: &'a event::Event<'b, ()>,
         should_update_ui: &'a mut bool,
         should_exit: &'a mut bool,
     },
+    /// A fixed step of the simulation, only while animating.
+    Tick {
+        step: Duration,
+    },
+    /// Set the widgets. `animating` asks for frames and ticks to keep coming
+    /// without events, as while playing; otherwise the loop waits for events.
     SetUi {
         needs_redraw: &'a mut bool,
+        animating: &'a mut bool,
     },
     Redraw,
 }
@@ -22,13 +35,14 @@ pub enum Request<'a, 'b: 'a> {
 /// screen.
 ///
 /// This function simplifies some of the boilerplate involved in limiting the redraw rate in the
-/// glutin+glium event loop.
-pub fn run_loop<F>(display: Display, event_loop: event_loop::EventLoop<()>, mut callback: F) -> !
+/// glutin+glium event loop. Outside of animations the UI is set at most every 16ms and only
+/// when events arrive; while animating, ticks come at a fixed rate and frames as often as
+/// `config` allows.
+pub fn run_loop<F>(display: Display, event_loop: event_loop::EventLoop<()>, config: LoopConfig, mut callback: F) -> !
 where
     F: 'static + FnMut(Request, &Display),
 {
-    let sixteen_ms = std::time::Duration::from_millis(16);
-    let mut next_update = None;
+    let mut schedule = Schedule::new(config, Instant::now());
     let mut ui_update_needed = false;
     event_loop.run(move |event, _, control_flow| {
         {
@@ -49,38 +63,51 @@ where
             }
         }
 
-        // We don't want to draw any faster than 60 FPS, so set the UI only on every 16ms, unless:
-        // - this is the very first event, or
-        // - we didn't request update on the last event and new events have arrived since then.
-        let should_set_ui_on_main_events_cleared = next_update.is_none() && ui_update_needed;
-        match (&event, should_set_ui_on_main_events_cleared) {
-            (event::Event::NewEvents(event::StartCause::Init { .. }), _)
-            | (event::Event::NewEvents(event::StartCause::ResumeTimeReached { .. }), _)
-            | (event::Event::MainEventsCleared, true) => {
-                next_update = Some(std::time::Instant::now() + sixteen_ms);
-                ui_update_needed = false;
-
-                let mut needs_redraw = false;
-                callback(
-                    Request::SetUi {
-                        needs_redraw: &mut needs_redraw,
-                    },
-                    &display,
-                );
-                if needs_redraw {
-                    display.gl_window().window().request_redraw();
-                } else {
-                    // We don't need to redraw anymore until more events arrives.
-                    next_update = None;
+        let set_ui = if schedule.is_animating() {
+            match event {
+                event::Event::MainEventsCleared | event::Event::NewEvents(event::StartCause::ResumeTimeReached { .. }) => {
+                    let now = Instant::now();
+                    for _ in 0..schedule.ticks(now) {
+                        callback(Request::Tick { step: schedule.tick_step() }, &display);
+                    }
+                    schedule.frame_due(now)
                 }
+                _ => false,
             }
-            _ => {}
-        }
-        if let Some(next_update) = next_update {
-            *control_flow = event_loop::ControlFlow::WaitUntil(next_update);
         } else {
-            *control_flow = event_loop::ControlFlow::Wait;
+            // We don't want to draw any faster than 60 FPS, so set the UI only on every 16ms, unless:
+            // - this is the very first event, or
+            // - we didn't request update on the last event and new events have arrived since then.
+            let should_set_ui_on_main_events_cleared = schedule.is_waiting() && ui_update_needed;
+            match (&event, should_set_ui_on_main_events_cleared) {
+                (event::Event::NewEvents(event::StartCause::Init { .. }), _)
+                | (event::Event::NewEvents(event::StartCause::ResumeTimeReached { .. }), _)
+                | (event::Event::MainEventsCleared, true) => {
+                    ui_update_needed = false;
+                    true
+                }
+                _ => false,
+            }
+        };
+        if set_ui {
+            let mut needs_redraw = false;
+            let mut animating = schedule.is_animating();
+            callback(
+                Request::SetUi {
+                    needs_redraw: &mut needs_redraw,
+                    animating: &mut animating,
+                },
+                &display,
+            );
+            if schedule.ui_set(Instant::now(), needs_redraw, animating) {
+                display.gl_window().window().request_redraw();
+            }
         }
+        *control_flow = match schedule.wake_up() {
+            WakeUp::Wait => event_loop::ControlFlow::Wait,
+            WakeUp::At(instant) => event_loop::ControlFlow::WaitUntil(instant),
+            WakeUp::Poll => event_loop::ControlFlow::Poll,
+        };
 
         // Request redraw if needed.
         match &event {
@@ -0,0 +1,165 @@
+// Typing state for phrase notes: the player types the phrase one character at
+// a time, can go back with backspace, and gets judged on both what was typed
+// and when it was typed.
+use crate::chart::{Phrase, PhraseTiming};
+use crate::judgement::{Judgement, Windows};
+
+/// Points given for a perfectly timed and typed character.
+pub const CHARACTER_POINTS: f64 = 100.0;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Keystroke {
+    /// The character matches the phrase at the cursor.
+    Correct,
+    /// The character does not match, it stays typed until erased.
+    Wrong,
+    /// The phrase is already fully typed, the character was ignored.
+    Ignored,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct TypedCharacter {
+    character: char,
+    time: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhraseInput {
+    target: Vec<char>,
+    typed: Vec<TypedCharacter>,
+    /// Every wrong character typed, even the ones erased afterwards.
+    mistakes: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PhraseResult {
+    /// One judgement per character of the phrase.
+    pub judgements: Vec<Judgement>,
+    pub correct: usize,
+    /// Every wrong character typed, erased or not.
+    pub mistakes: usize,
+    /// The wrong characters that were erased, and so typed twice.
+    pub erased: usize,
+}
+
+impl PhraseInput {
+    pub fn new(phrase: &Phrase) -> PhraseInput {
+        PhraseInput {
+            target: phrase.text.chars().collect(),
+            typed: Vec::new(),
+            mistakes: 0,
+        }
+    }
+
+    /// Type `character` at media time `time`.
+    pub fn type_char(&mut self, character: char, time: f64) -> Keystroke {
+        let expected = match self.target.get(self.typed.len()) {
+            Some(expected) => *expected,
+            None => return Keystroke::Ignored,
+        };
+        self.typed.push(TypedCharacter { character, time });
+        if character == expected {
+            Keystroke::Correct
+        } else {
+            self.mistakes += 1;
+            Keystroke::Wrong
+        }
+    }
+
+    /// Erase the last typed character.
+    pub fn backspace(&mut self) {
+        self.typed.pop();
+    }
+
+    /// Index of the character the player has to type next.
+    pub fn cursor(&self) -> usize {
+        self.typed.len()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.typed.len() == self.target.len()
+    }
+
+    /// Whether everything typed so far matches the phrase.
+    pub fn is_correct_so_far(&self) -> bool {
+        self.typed
+            .iter()
+            .zip(self.target.iter())
+            .all(|(typed, expected)| typed.character == *expected)
+    }
+
+    /// Judge the typed characters against the phrase timing. Characters
+    /// missing or typed wrong are a `Miss`.
+    pub fn result(&self, phrase: &Phrase, start: f64, windows: &Windows) -> PhraseResult {
+        let judgements: Vec<Judgement> = self.target
+            .iter()
+            .enumerate()
+            .map(|(index, expected)| match self.typed.get(index) {
+                Some(typed) if typed.character == *expected => {
+                    judge_character(phrase, index, start, typed.time, windows)
+                }
+                _ => Judgement::Miss,
+            })
+            .collect();
+        let correct = self.typed
+            .iter()
+            .zip(self.target.iter())
+            .filter(|(typed, expected)| typed.character == **expected)
+            .count();
+        // The wrong characters still typed are already misses
+        let wrong = self.typed.len().min(self.target.len()) - correct;
+        PhraseResult {
+            judgements,
+            correct,
+            mistakes: self.mistakes,
+            erased: self.mistakes.saturating_sub(wrong),
+        }
+    }
+}
+
+fn judge_character(phrase: &Phrase, index: usize, start: f64, time: f64, windows: &Windows) -> Judgement {
+    match phrase.timing {
+        PhraseTiming::PerCharacter(_) => {
+            let target = phrase.target_time(index).unwrap_or(start);
+            windows.judge(time - target).unwrap_or(Judgement::Miss)
+        }
+        // Anything inside the window is perfect, a bit outside of it is
+        // judged like an early or late key.
+        PhraseTiming::Window { end } => {
+            let offset = if time < start {
+                time - start
+            } else if time > end {
+                time - end
+            } else {
+                0.0
+            };
+            windows.judge(offset).unwrap_or(Judgement::Miss)
+        }
+    }
+}
+
+impl PhraseResult {
+    /// Fraction of the typed characters that were right, counting the
+    /// erased mistakes against the player.
+    pub fn typing_accuracy(&self) -> f64 {
+        let attempts = self.judgements.len() + self.erased;
+        if attempts == 0 {
+            return 1.0;
+        }
+        self.correct as f64 / attempts as f64
+    }
+
+    /// Mean judgement weight over all the characters of the phrase.
+    pub fn timing_accuracy(&self) -> f64 {
+        if self.judgements.is_empty() {
+            return 1.0;
+        }
+        let total: f64 = self.judgements.iter().map(|j| j.weight()).sum();
+        total / self.judgements.len() as f64
+    }
+
+    pub fn score(&self) -> u64 {
+        let full = CHARACTER_POINTS * self.judgements.len() as f64;
+        (full * self.typing_accuracy() * self.timing_accuracy()).round() as u64
+    }
+}
@@ -0,0 +1,43 @@
+// Calibration finds the mean tap offset around the beats, without the taps
+// that were mistakes.
+use mechanical::calibration::{self, Calibration};
+
+const EPSILON: f64 = 1e-9;
+
+#[test]
+fn taps_are_compared_with_the_closest_beat() {
+    let mut calibration = Calibration::new(0.5);
+    calibration.tap(0.52);
+    calibration.tap(0.98);
+    calibration.tap(10.24);
+    let offsets = calibration.offsets();
+    assert!((offsets[0] - 0.02).abs() < EPSILON);
+    assert!((offsets[1] + 0.02).abs() < EPSILON);
+    assert!((offsets[2] - 0.24).abs() < EPSILON);
+}
+
+#[test]
+fn outliers_do_not_move_the_offset() {
+    let mut calibration = Calibration::new(0.5);
+    let steady = [0.030, 0.034, 0.026, 0.031, 0.029, 0.033, 0.027, 0.030, 0.032, 0.028];
+    for (beat, offset) in steady.iter().enumerate() {
+        calibration.tap(beat as f64 * 0.5 + offset);
+    }
+    // Taps about a tenth of a second off, late and early
+    calibration.tap(20.13);
+    calibration.tap(21.42);
+    assert_eq!(calibration.kept().len(), steady.len());
+    assert!((calibration.offset().unwrap() - 0.030).abs() < EPSILON);
+}
+
+#[test]
+fn too_few_steady_taps_give_no_offset() {
+    let mut calibration = Calibration::new(1.0);
+    for beat in 0..calibration::MIN_TAPS - 1 {
+        calibration.tap(beat as f64 + 0.01);
+    }
+    assert_eq!(calibration.offset(), None);
+    calibration.tap(20.01);
+    assert!((calibration.offset().unwrap() - 0.01).abs() < EPSILON);
+    assert!(calibration::inliers(&[]).is_empty());
+}
@@ -0,0 +1,117 @@
+// The media time gameplay reads: manual and offset clocks, events timed
+// back to when they happened, and the player position moved forward between
+// its queries.
+use std::time::{Duration, Instant};
+
+use mechanical::clock::{self, GameClock, Interpolation, ManualClock, OffsetClock};
+
+// Room for the time it takes to run the test between two instants
+const SLACK: f64 = 0.05;
+
+// A media played at twice the normal speed
+struct FastClock(f64);
+
+impl GameClock for FastClock {
+    fn time(&self) -> f64 {
+        self.0
+    }
+
+    fn rate(&self) -> f64 {
+        2.0
+    }
+}
+
+fn assert_near(time: f64, expected: f64) {
+    assert!(time <= expected && time > expected - SLACK, "{} is not {}", time, expected);
+}
+
+fn assert_same(time: f64, expected: f64) {
+    assert!((time - expected).abs() < 1e-9, "{} is not {}", time, expected);
+}
+
+#[test]
+fn manual_clocks_move_when_told() {
+    let mut clock = ManualClock::new(1.0);
+    assert_eq!(clock.time(), 1.0);
+    assert_eq!(clock.rate(), 1.0);
+    clock.advance(0.5);
+    assert_eq!(clock.time(), 1.5);
+    clock.set(0.25);
+    assert_eq!(clock.time(), 0.25);
+}
+
+#[test]
+fn events_are_timed_when_they_happened() {
+    let clock = ManualClock::new(10.0);
+    assert_near(clock.time_at(Instant::now()), 10.0);
+    assert_near(clock.time_at(Instant::now() - Duration::from_millis(500)), 9.5);
+    // An instant not yet passed is now
+    assert_near(clock.time_at(Instant::now() + Duration::from_secs(1)), 10.0);
+
+    // Real seconds are media seconds times the rate
+    let fast = FastClock(10.0);
+    assert_near(fast.time_at(Instant::now() - Duration::from_millis(500)), 9.0);
+}
+
+#[test]
+fn offset_clocks_are_set_back() {
+    let fast = FastClock(10.0);
+    let heard = OffsetClock::new(&fast, 0.25);
+    assert_eq!(heard.time(), 9.75);
+    assert_eq!(heard.rate(), 2.0);
+    assert_near(heard.time_at(Instant::now() - Duration::from_millis(500)), 8.75);
+
+    let clock = ManualClock::new(1.0);
+    let dyn_clock: &dyn GameClock = &clock;
+    assert_eq!(OffsetClock::new(dyn_clock, -0.5).time(), 1.5);
+}
+
+#[test]
+fn positions_are_interpolated_between_queries() {
+    let mut interpolation = Interpolation::default();
+    assert_same(interpolation.time(0.0, true, || 2.0), 2.0);
+    // Moved forward with the pipeline clock without querying
+    assert_same(interpolation.time(0.05, true, || unreachable!()), 2.05);
+    // Queried again once the last query is too old
+    let later = clock::QUERY_INTERVAL;
+    assert_same(interpolation.time(later, true, || 2.2), 2.2);
+    assert_same(interpolation.time(later + 0.01, true, || unreachable!()), 2.21);
+}
+
+#[test]
+fn interpolation_follows_the_rate() {
+    let mut interpolation = Interpolation::default();
+    interpolation.seek(10.0, 0.5);
+    assert_eq!(interpolation.rate(), 0.5);
+    assert_same(interpolation.time(0.0, true, || 10.0), 10.0);
+    assert_same(interpolation.time(0.08, true, || unreachable!()), 10.04);
+    assert_eq!(interpolation.media_seconds(2.0, true), 1.0);
+
+    // Seeking forgets the last query
+    interpolation.seek(20.0, 2.0);
+    assert_same(interpolation.time(0.09, true, || 20.0), 20.0);
+    assert_same(interpolation.time(0.14, true, || unreachable!()), 20.1);
+    assert_eq!(interpolation.media_seconds(2.0, true), 4.0);
+}
+
+#[test]
+fn paused_media_is_always_queried() {
+    let mut interpolation = Interpolation::default();
+    assert_same(interpolation.time(0.0, false, || 5.0), 5.0);
+    assert_same(interpolation.time(0.01, false, || 5.0), 5.0);
+    // Going back while paused is a seek, not jitter
+    assert_same(interpolation.time(0.02, false, || 4.99), 4.99);
+    assert_eq!(interpolation.media_seconds(2.0, false), 0.0);
+}
+
+#[test]
+fn jitter_does_not_move_the_clock_back() {
+    let mut interpolation = Interpolation::default();
+    assert_same(interpolation.time(0.0, true, || 1.0), 1.0);
+    assert_same(interpolation.time(0.09, true, || unreachable!()), 1.09);
+    // The position queried is a little behind what was interpolated
+    assert_same(interpolation.time(0.1, true, || 1.07), 1.09);
+    assert_same(interpolation.time(0.15, true, || unreachable!()), 1.12);
+    // Further behind than jitter, the clock goes back
+    assert_same(interpolation.time(0.25, true, || 1.0), 1.0);
+}
@@ -0,0 +1,240 @@
+// The converters read notes, holds and timing as the formats mean them.
+// Converted files are user input: values that parse but make no chart are
+// refused with an error instead of a panic.
+use mechanical::chart::{Chart, Note, NoteKind};
+use mechanical::convert::{midi, osu, stepmania, ColumnKeys};
+
+fn beatmap(circle_size: &str, hit_objects: &str) -> String {
+    format!(
+        "osu file format v14\n\n[General]\nAudioFilename: song.ogg\nMode: 3\n\n[Metadata]\nTitle:Song\n\n\
+         [Difficulty]\nCircleSize:{}\n\n[TimingPoints]\n0,500,4,2,0,100,1,0\n\n[HitObjects]\n{}\n",
+        circle_size, hit_objects
+    )
+}
+
+#[test]
+fn osu_beatmaps_become_charts() {
+    let hit_objects = "64,192,1000,1,0,0:0:0:0:\n192,192,1500,128,0,2000:0:0:0:0:\n\
+                       320,192,2000,1,2,0:0:0:0:\n448,192,2500,2,0,B|448:100,1,100";
+    let contents = beatmap("4", hit_objects)
+        .replace("0,500,4,2,0,100,1,0", "0,500,4,2,0,100,1,0\n1000,-50,4,2,0,100,0,0\n2000,250,3,2,0,100,1,0");
+    let (chart, report) = osu::import(&contents, None).unwrap();
+    assert_eq!(chart.metadata.title, "Song");
+    assert_eq!(chart.metadata.audio, "song.ogg");
+    // Columns left to right on the home row keys
+    assert_eq!(chart.notes, [Note::key(1.0, 'd'), Note::hold(1.5, 'f', 2.0), Note::key(2.0, 'j')]);
+    let points: Vec<(f64, f64, u32)> =
+        chart.timing_points.iter().map(|point| (point.time, point.bpm, point.meter)).collect();
+    assert_eq!(points, [(0.0, 120.0, 4), (2.0, 240.0, 3)]);
+    // The scroll speed change, the hit sound and the slider
+    let dropped = report.to_string().lines().filter(|line| line.starts_with("  - ")).count();
+    assert_eq!(dropped, 3, "{}", report);
+}
+
+const CHART: &str = "[Metadata]\ntitle=Song\nartist=Band\ncreator=Someone\ndifficulty=Hard\naudio=song.ogg\n\
+                     [Timing]\n0.000 120.000 4\n2.000 150.000 3\n\
+                     [Notes]\n1.000 key a\n1.250 key s\n1.500 hold d 2.500\n2.000 key k\n3.000 typed 3.000,3.250 sk\n";
+
+#[test]
+fn osu_exports_import_back() {
+    let chart = Chart::parse(CHART).unwrap();
+    let (contents, report) = osu::export(&chart, None).unwrap();
+    assert!(report.is_empty(), "{}", report);
+    let (imported, report) = osu::import(&contents, Some(ColumnKeys::parse("asdk"))).unwrap();
+    assert!(report.is_empty(), "{}", report);
+    assert_eq!(imported.metadata, chart.metadata);
+    assert_eq!(imported.timing_points, chart.timing_points);
+    // Phrases typed character by character come back as keys
+    let notes = [
+        Note::key(1.0, 'a'),
+        Note::key(1.25, 's'),
+        Note::hold(1.5, 'd', 2.5),
+        Note::key(2.0, 'k'),
+        Note::key(3.0, 's'),
+        Note::key(3.25, 'k'),
+    ];
+    assert_eq!(imported.notes, notes);
+
+    // One column per key in keyboard order, or in the order given
+    let keys_of = |chart: &Chart| -> String {
+        chart
+            .notes
+            .iter()
+            .map(|note| match note.kind {
+                NoteKind::Key(key) | NoteKind::Hold { key, .. } => key,
+                NoteKind::Phrase(_) => '?',
+            })
+            .collect()
+    };
+    let (imported, _) = osu::import(&contents, None).unwrap();
+    assert_eq!(keys_of(&imported), "dfjkfk");
+    let (contents, _) = osu::export(&chart, Some(&ColumnKeys::parse("kdsa"))).unwrap();
+    let (imported, _) = osu::import(&contents, None).unwrap();
+    assert_eq!(keys_of(&imported), "kjfdjd");
+}
+
+#[test]
+fn osu_holds_and_audio_must_make_sense() {
+    let notes = "64,192,1000,1,0,0:0:0:0:";
+    assert!(osu::import(&beatmap("4", "64,192,1000,128,0,1000:0:0:0:0:"), None).is_err());
+    assert!(osu::import(&beatmap("4", "64,192,1000,128,0,500:0:0:0:0:"), None).is_err());
+    assert!(osu::import(&beatmap("4", notes).replace("AudioFilename: song.ogg\n", ""), None).is_err());
+    assert!(osu::import(&beatmap("4", notes).replace("song.ogg", ""), None).is_err());
+}
+
+#[test]
+fn osu_key_counts_must_be_playable() {
+    let notes = "64,192,1000,1,0,0:0:0:0:";
+    assert!(osu::import(&beatmap("4", notes), None).is_ok());
+    for circle_size in &["0", "11", "-1", "nan", "inf"] {
+        let keys = Some(ColumnKeys::parse("asdf"));
+        assert!(osu::import(&beatmap(circle_size, notes), keys).is_err(), "{}", circle_size);
+    }
+}
+
+#[test]
+fn osu_times_must_be_finite() {
+    assert!(osu::import(&beatmap("4", "64,192,nan,1,0,0:0:0:0:"), None).is_err());
+    assert!(osu::import(&beatmap("4", "64,192,1000,128,0,inf:0:0:0:0:"), None).is_err());
+
+    let mut chart = Chart::default();
+    chart.add_note(Note::key(1.0, 'a'));
+    chart.add_note(Note::key(f64::NAN, 's'));
+    assert!(osu::export(&chart, None).is_err());
+}
+
+fn simfile(bpms: &str, stops: &str) -> String {
+    format!(
+        "#TITLE:Song;\n#MUSIC:song.ogg;\n#OFFSET:0;\n#BPMS:{};\n#STOPS:{};\n\
+         #NOTES:\n     dance-single:\n     author:\n     Easy:\n     1:\n     0,0,0,0,0:\n\
+         1000\n0100\n0010\n0001\n;\n",
+        bpms, stops
+    )
+}
+
+#[test]
+fn stepmania_beat_values_must_be_finite() {
+    let (charts, _) = stepmania::import(&simfile("0=120", ""), None).unwrap();
+    assert_eq!(charts[0].notes.len(), 4);
+    // Warps are dropped, not refused
+    let (_, report) = stepmania::import(&simfile("0=120,2=0", ""), None).unwrap();
+    assert!(!report.is_empty());
+    for (bpms, stops) in &[("0=nan", ""), ("inf=120", ""), ("0=120,NaN=140", ""), ("0=120", "1=inf")] {
+        assert!(stepmania::import(&simfile(bpms, stops), None).is_err(), "{} {}", bpms, stops);
+    }
+}
+
+#[test]
+fn stepmania_stops_and_bpm_changes_move_the_notes_after_them() {
+    // Beat 0 a quarter of a second in, 120 then 240 BPM from beat 4, and
+    // half a second stopped on beat 2
+    let contents = "#TITLE:Song;\n#MUSIC:song.ogg;\n#OFFSET:-0.25;\n#BPMS:0=120,4=240;\n#STOPS:2=0.5;\n\
+                    #NOTES:dance-single::Hard:1:0,0,0,0,0:\n1000\n0100\n0010\n0001\n,\n1000\n0100\n0000\n0000\n;\n";
+    let (charts, _) = stepmania::import(contents, None).unwrap();
+    let times: Vec<f64> = charts[0].notes.iter().map(|note| note.time).collect();
+    // The note on the stop is not delayed, the ones after it are
+    assert_eq!(times, [0.25, 0.75, 1.25, 2.25, 2.75, 3.0]);
+    let points: Vec<(f64, f64)> = charts[0].timing_points.iter().map(|point| (point.time, point.bpm)).collect();
+    assert_eq!(points, [(0.25, 120.0), (2.75, 240.0)]);
+}
+
+#[test]
+fn ssc_charts_can_have_their_own_bpms() {
+    let chart = |bpms: &str| {
+        format!(
+            "#NOTEDATA:;\n#STEPSTYPE:dance-single;\n#DIFFICULTY:Hard;\n{}#NOTES:\n1000\n0100\n0010\n0001\n;\n",
+            bpms
+        )
+    };
+    let contents = format!("#TITLE:Song;\n#MUSIC:song.ogg;\n#OFFSET:0;\n{}", chart("#BPMS:0=240;\n"));
+    let (charts, _) = stepmania::import(&contents, None).unwrap();
+    let times: Vec<f64> = charts[0].notes.iter().map(|note| note.time).collect();
+    assert_eq!(times, [0.0, 0.25, 0.5, 0.75]);
+
+    let contents = format!("#TITLE:Song;\n#MUSIC:song.ogg;\n#OFFSET:0;\n{}", chart(""));
+    assert!(stepmania::import(&contents, None).is_err());
+}
+
+// A Standard MIDI File of `tracks`, each ended for them
+fn midi_file(division: [u8; 2], tracks: &[&[u8]]) -> Vec<u8> {
+    let mut bytes = b"MThd\x00\x00\x00\x06\x00\x01".to_vec();
+    bytes.extend_from_slice(&(tracks.len() as u16).to_be_bytes());
+    bytes.extend_from_slice(&division);
+    for track in tracks {
+        bytes.extend_from_slice(b"MTrk");
+        bytes.extend_from_slice(&(track.len() as u32 + 4).to_be_bytes());
+        bytes.extend_from_slice(track);
+        bytes.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+    }
+    bytes
+}
+
+// 96 ticks per quarter note
+const QUARTER: [u8; 2] = [0x00, 0x60];
+
+#[test]
+fn midi_long_notes_become_holds() {
+    let track: &[u8] = &[
+        0x00, 0x90, 0x3c, 0x40, 0x60, 0x80, 0x3c, 0x40, // middle C for a quarter
+        0x00, 0x90, 0x3e, 0x40, 0x18, 0x80, 0x3e, 0x40, // D for a sixteenth
+    ];
+    let (chart, report) = midi::import(&midi_file(QUARTER, &[track]), &midi::Options::default()).unwrap();
+    assert!(report.is_empty());
+    assert_eq!(chart.notes, [Note::hold(0.0, 'a', 0.5), Note::key(0.5, 's')]);
+}
+
+#[test]
+fn midi_tempo_changes_move_the_notes_after_them() {
+    let tempo: &[u8] = &[
+        0x00, 0xff, 0x51, 0x03, 0x07, 0xa1, 0x20, // 120 BPM
+        0x81, 0x40, 0xff, 0x51, 0x03, 0x03, 0xd0, 0x90, // 240 BPM after two beats
+    ];
+    let notes: &[u8] = &[0x83, 0x00, 0x90, 0x3c, 0x40, 0x00, 0x80, 0x3c, 0x40];
+    let (chart, _) = midi::import(&midi_file(QUARTER, &[tempo, notes]), &midi::Options::default()).unwrap();
+    let points: Vec<(f64, f64)> = chart.timing_points.iter().map(|point| (point.time, point.bpm)).collect();
+    assert_eq!(points, [(0.0, 120.0), (1.0, 240.0)]);
+    assert_eq!(chart.notes, [Note::key(1.5, 'a')]);
+}
+
+#[test]
+fn midi_smpte_divisions_count_ticks_per_second() {
+    let track: &[u8] = &[0x83, 0x74, 0x90, 0x3c, 0x40, 0x00, 0x80, 0x3c, 0x40];
+    // 25 frames of 40 ticks a second
+    let (chart, _) = midi::import(&midi_file([0xe7, 0x28], &[track]), &midi::Options::default()).unwrap();
+    assert_eq!(chart.notes, [Note::key(0.5, 'a')]);
+    for division in &[[0x80, 0x28], [0xe7, 0x00], [0xf0, 0x28]] {
+        assert!(midi::import(&midi_file(*division, &[track]), &midi::Options::default()).is_err());
+    }
+}
+
+#[test]
+fn midi_notes_never_released_are_kept_in_order() {
+    let track: &[u8] = &[
+        0x00, 0x90, 0x3e, 0x40, 0x00, 0x90, 0x3c, 0x40, 0x00, 0x90, 0x40, 0x40, // D, C and E
+        0x60, 0x90, 0x3d, 0x40, // C sharp a beat later
+    ];
+    let (chart, report) = midi::import(&midi_file(QUARTER, &[track]), &midi::Options::default()).unwrap();
+    assert!(!report.is_empty());
+    let keys: Vec<(f64, char)> = chart
+        .notes
+        .iter()
+        .map(|note| match note.kind {
+            NoteKind::Key(key) => (note.time, key),
+            _ => panic!("{:?} is not a tap", note),
+        })
+        .collect();
+    assert_eq!(keys, [(0.0, 'a'), (0.0, 's'), (0.0, 'd'), (0.5, 'w')]);
+}
+
+#[test]
+fn midi_zero_tempo_is_the_shortest_one() {
+    let track: &[u8] = &[
+        0x00, 0xff, 0x51, 0x03, 0x00, 0x00, 0x00, // tempo 0 at tick 0
+        0x00, 0x90, 0x3c, 0x40, 0x00, 0x80, 0x3c, 0x40, // a quarter apart
+        0x60, 0x90, 0x3e, 0x40, 0x00, 0x80, 0x3e, 0x40,
+    ];
+    let (chart, _) = midi::import(&midi_file(QUARTER, &[track]), &midi::Options::default()).unwrap();
+    assert!(chart.timing_points.iter().all(|point| point.bpm.is_finite()));
+    assert_eq!(chart.notes.len(), 2);
+    assert!(chart.notes[1].time > chart.notes[0].time);
+}
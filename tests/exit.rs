@@ -0,0 +1,18 @@
+// Commands given a file that does not exist exit as with wrong arguments,
+// other errors as failures.
+use mechanical::chart::Chart;
+use mechanical::exit;
+use mechanical::scores::ScoreDatabase;
+
+#[test]
+fn missing_files_are_usage_errors() {
+    let missing = std::env::temp_dir().join(format!("mechanical-exit-{}.chart", std::process::id()));
+    let err = Chart::load(&missing).unwrap_err();
+    assert_eq!(exit::code(&err), exit::USAGE);
+
+    let err = Chart::parse("[Notes]\nnot a note\n").unwrap_err();
+    assert_eq!(exit::code(&err), exit::FAILURE);
+    let directory = std::env::temp_dir();
+    let err = ScoreDatabase::open(&directory).unwrap_err();
+    assert_eq!(exit::code(&err), exit::FAILURE);
+}
@@ -0,0 +1,95 @@
+// The highway layout: notes on their way down to the judgement line of the
+// lanes, gone once hit, and a flash fading over the lane of the last one.
+use mechanical::chart::{Chart, Note};
+use mechanical::gameplay::Gameplay;
+use mechanical::highway::{self, Ink, Paint, Rect, JUDGEMENT_LINE_MARGIN, JUDGEMENT_SHOWN};
+use mechanical::judgement::Judgement;
+use mechanical::profile::Profile;
+
+const EPSILON: f64 = 1e-9;
+const AREA: Rect = Rect {
+    x: 0.0,
+    y: 0.0,
+    w: 800.0,
+    h: 600.0,
+};
+
+fn notes(frame: &highway::Frame) -> Vec<&highway::Quad> {
+    frame
+        .quads
+        .iter()
+        .filter(|quad| quad.paint == Paint::Note || quad.paint == Paint::Hold)
+        .collect()
+}
+
+#[test]
+fn notes_scroll_down_their_lane() {
+    let chart = Chart::parse("[Notes]\n1.000 key s\n2.000 key a\n1.500 hold a 2.000\n").unwrap();
+    let gameplay = Gameplay::new(Some(chart));
+    let mut profile = Profile::new("player");
+    profile.bind('j', 'a');
+    let frame = highway::frame(&gameplay, &profile, AREA, 1.0);
+
+    let line_y = AREA.bottom() + JUDGEMENT_LINE_MARGIN;
+    assert_eq!(frame.quads[0].paint, Paint::Line);
+    assert!((frame.quads[0].rect.y - line_y).abs() < EPSILON);
+    let keys: Vec<&str> = frame
+        .labels
+        .iter()
+        .filter(|label| label.ink == Ink::Key)
+        .map(|label| label.text.as_str())
+        .collect();
+    assert_eq!(keys, ["j", "s"]);
+
+    let notes = notes(&frame);
+    assert_eq!(notes.len(), 3);
+    // The note due now sits on the line, in the lane on the right
+    let due = notes.iter().find(|quad| quad.rect.x > 0.0).unwrap();
+    assert!((due.rect.bottom() - line_y).abs() < EPSILON);
+    let hold = notes.iter().find(|quad| quad.paint == Paint::Hold).unwrap();
+    assert!((hold.rect.bottom() - (line_y + 0.5 * profile.scroll_speed)).abs() < EPSILON);
+    assert!((hold.rect.h - 0.5 * profile.scroll_speed).abs() < EPSILON);
+}
+
+#[test]
+fn hit_notes_leave_and_flash() {
+    let chart = Chart::parse("[Notes]\n1.000 key a\n5.000 key a\n").unwrap();
+    let mut gameplay = Gameplay::new(Some(chart));
+    let profile = Profile::new("player");
+    gameplay.key_down('a', 1.0);
+    gameplay.update(1.0);
+
+    let frame = highway::frame(&gameplay, &profile, AREA, 1.1);
+    // The next note is still beyond the top of the highway
+    assert!(notes(&frame).is_empty());
+    let flash = frame
+        .quads
+        .iter()
+        .find(|quad| quad.paint == Paint::Flash(Judgement::Perfect))
+        .unwrap();
+    assert!((flash.alpha - (1.0 - 0.1 / JUDGEMENT_SHOWN)).abs() < EPSILON);
+    assert!(frame
+        .labels
+        .iter()
+        .any(|label| label.ink == Ink::Judgement(Judgement::Perfect)));
+
+    let later = highway::frame(&gameplay, &profile, AREA, 1.0 + JUDGEMENT_SHOWN);
+    assert!(later.quads.iter().all(|quad| quad.paint == Paint::Line));
+}
+
+#[test]
+fn thousands_of_notes_are_all_laid_out() {
+    let profile = Profile::new("player");
+    let look_ahead = (AREA.h - JUDGEMENT_LINE_MARGIN) / profile.scroll_speed;
+    let mut chart = Chart::default();
+    for index in 0..5000 {
+        chart.add_note(Note::key(
+            look_ahead * index as f64 / 5000.0,
+            "asdfjkl;".chars().nth(index % 8).unwrap(),
+        ));
+    }
+    let gameplay = Gameplay::new(Some(chart));
+    let frame = highway::frame(&gameplay, &profile, AREA, 0.0);
+    assert_eq!(notes(&frame).len(), 5000);
+    assert!(notes(&frame).iter().all(|quad| quad.rect.bottom() <= AREA.top()));
+}
@@ -0,0 +1,51 @@
+// Keys queued when they arrive keep the media time they arrived at, however
+// late the gameplay is updated.
+use std::thread;
+use std::time::{Duration, Instant};
+
+use mechanical::chart::Chart;
+use mechanical::clock::{GameClock, ManualClock};
+use mechanical::gameplay::Gameplay;
+use mechanical::input::InputQueue;
+use mechanical::judgement::Judgement;
+use mechanical::replay::KeyAction;
+
+// Time passes between reading the clock and draining the queue
+const TOLERANCE: f64 = 0.01;
+
+#[test]
+fn clock_times_past_instants() {
+    let clock = ManualClock::new(10.0);
+    let instant = Instant::now() - Duration::from_millis(250);
+    assert!((clock.time_at(instant) - 9.75).abs() < TOLERANCE);
+    // Instants to come are not guessed
+    assert!((clock.time_at(Instant::now() + Duration::from_secs(1)) - 10.0).abs() < TOLERANCE);
+}
+
+#[test]
+fn queued_keys_keep_their_arrival_time() {
+    let mut queue = InputQueue::new();
+    let pressed = Instant::now();
+    queue.push(pressed, 'a', KeyAction::Down);
+    thread::sleep(Duration::from_millis(40));
+    let released = Instant::now();
+    queue.push(released, 'a', KeyAction::Up);
+    // The update comes a frame later
+    thread::sleep(Duration::from_millis(20));
+    let clock = ManualClock::new(1.0 + pressed.elapsed().as_secs_f64());
+
+    let inputs = queue.drain(&clock);
+    assert!(queue.is_empty());
+    assert_eq!(inputs.len(), 2);
+    assert_eq!(inputs[0].action, KeyAction::Down);
+    assert!((inputs[0].time - 1.0).abs() < TOLERANCE, "{}", inputs[0].time);
+    let held = (released - pressed).as_secs_f64();
+    assert!((inputs[1].time - 1.0 - held).abs() < TOLERANCE, "{}", inputs[1].time);
+
+    let mut gameplay = Gameplay::new(Some(Chart::parse("[Notes]\n1.000 key a\n").unwrap()));
+    for input in &inputs {
+        gameplay.input(input);
+    }
+    gameplay.update(clock.time());
+    assert_eq!(gameplay.score.count(Judgement::Perfect), 1);
+}
@@ -0,0 +1,167 @@
+// Every problem the linter reports, on the line it points at, and the JSON
+// the tools read the diagnostics from.
+use mechanical::lint::{self, Diagnostic, Options, Severity};
+
+// Complete metadata, the first note is on line 7
+const METADATA: &str = "[Metadata]\ntitle=Test\nartist=Artist\ncreator=Creator\naudio=test.ogg\n[Notes]\n";
+
+fn lint_notes(notes: &str, options: &Options) -> Vec<Diagnostic> {
+    lint::lint(&format!("{}{}", METADATA, notes), options)
+}
+
+fn messages(diagnostics: &[Diagnostic]) -> Vec<String> {
+    diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect()
+}
+
+#[test]
+fn a_clean_chart_has_no_diagnostics() {
+    let notes = "1.000 key a\n1.500 hold space 2.000\n2.000 phrase 3.000 text\n4.000 typed 4.000,4.100,4.200 hey\n";
+    let options = Options { media_duration: Some(10.0), layout: None };
+    assert_eq!(lint_notes(notes, &options), vec![]);
+}
+
+#[test]
+fn metadata_problems() {
+    let contents = "# comment\n[Metadata]\ndifficulty=Hard\ntitle\nunknown=1\n[Notes]\n1.000 key a\n";
+    let diagnostics = lint::lint(contents, &Options::default());
+    assert_eq!(
+        messages(&diagnostics),
+        vec![
+            "3: error: the chart has no title",
+            "3: error: the chart has no audio file",
+            "3: warning: the chart has no artist",
+            "3: warning: the chart has no creator",
+            "4: error: metadata line without '=': title",
+            "5: error: unknown metadata unknown",
+        ]
+    );
+}
+
+#[test]
+fn charts_without_notes() {
+    let diagnostics = lint_notes("", &Options::default());
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic { severity: Severity::Error, line: None, message: "the chart has no notes".to_string() }]
+    );
+    assert_eq!(diagnostics[0].to_string(), "error: the chart has no notes");
+}
+
+#[test]
+fn lines_that_do_not_parse_are_reported_and_skipped() {
+    let diagnostics = lint_notes("1.000 key a\nsoon key b\n2.000 slide c\n3.000 key c\n", &Options::default());
+    assert_eq!(
+        messages(&diagnostics),
+        vec!["8: error: invalid time soon: invalid float literal", "9: error: unknown note kind slide"]
+    );
+}
+
+#[test]
+fn note_times() {
+    let options = Options { media_duration: Some(5.0), layout: None };
+    let notes = "2.000 key a\n1.000 key b\n-0.500 key c\n3.000 hold d 2.500\n4.000 phrase 6.000 late\n";
+    assert_eq!(
+        messages(&lint_notes(notes, &options)),
+        vec![
+            "8: error: note at 1.000 comes after a note at 2.000",
+            "9: error: note at -0.500 comes after a note at 1.000",
+            "9: error: note at -0.500 is before the start of the media",
+            "10: error: note ends at 2.500, before it starts",
+            "11: error: note ends at 6.000, after the end of the media at 5.000",
+        ]
+    );
+}
+
+#[test]
+fn notes_of_the_same_key_can_not_overlap() {
+    let notes = "1.000 hold a 2.000\n1.500 key a\n2.500 key a\n2.5008 key a\n3.000 key a\n";
+    assert_eq!(
+        messages(&lint_notes(notes, &Options::default())),
+        vec![
+            "8: error: 'a' at 1.500 overlaps the note on line 7",
+            "10: error: 'a' at 2.501 overlaps the note on line 9",
+        ]
+    );
+}
+
+#[test]
+fn phrase_problems() {
+    let notes = "1.000 phrase 2.000\n3.000 typed 3.000,3.100 hey\n4.000 typed 4.000,4.200,4.100 you\n";
+    assert_eq!(
+        messages(&lint_notes(notes, &Options::default())),
+        vec![
+            "7: error: phrase without text",
+            "8: error: 2 times for the 3 characters of \"hey\"",
+            "9: error: character times of \"you\" are not sorted",
+        ]
+    );
+}
+
+#[test]
+fn keys_must_be_on_the_layout() {
+    let options = Options { media_duration: None, layout: Some("asdf ".chars().collect()) };
+    let notes = "1.000 key a\n2.000 key q\n3.000 phrase 4.000 sad queue\n";
+    assert_eq!(
+        messages(&lint_notes(notes, &options)),
+        vec![
+            "8: error: 'q' can't be typed with the selected layout",
+            "9: error: 'q' can't be typed with the selected layout",
+            "9: error: 'u' can't be typed with the selected layout",
+            "9: error: 'e' can't be typed with the selected layout",
+        ]
+    );
+
+    // Without a layout, anything printable on a US keyboard
+    let diagnostics = lint_notes("1.000 key é\n", &Options::default());
+    assert_eq!(messages(&diagnostics), vec!["7: error: 'é' can't be typed with the selected layout"]);
+}
+
+#[test]
+fn too_many_presses_in_one_second() {
+    let length = lint::MAX_PRESSES_PER_SECOND;
+    let fast: String = "abcdefghijklmnopqrstuvwxyz".chars().take(length + 1).collect();
+    let slow: String = "abcdefghijklmnopqrstuvwxyz".chars().take(length).collect();
+    let notes = format!("1.000 phrase 2.000 {}\n3.000 phrase 4.000 {}\n", fast, slow);
+    let diagnostics = lint_notes(&notes, &Options::default());
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic {
+            severity: Severity::Warning,
+            line: Some(7),
+            message: format!("more than {} presses in one second from 1.000", length),
+        }]
+    );
+}
+
+#[test]
+fn too_many_keys_at_once() {
+    let chord: String = "abcdefg".chars().map(|key| format!("1.000 key {}\n", key)).collect();
+    let playable: String = "abcdef".chars().map(|key| format!("2.000 key {}\n", key)).collect();
+    let diagnostics = lint_notes(&format!("{}{}", chord, playable), &Options::default());
+    assert_eq!(
+        diagnostics,
+        vec![Diagnostic {
+            severity: Severity::Warning,
+            line: Some(7),
+            message: format!("7 keys pressed at once at 1.000, at most {} can be played", lint::MAX_CHORD),
+        }]
+    );
+}
+
+#[test]
+fn json_output() {
+    assert_eq!(lint::to_json(&[]), "[]");
+    let diagnostics = vec![
+        Diagnostic { severity: Severity::Error, line: None, message: "the chart has no notes".to_string() },
+        Diagnostic {
+            severity: Severity::Warning,
+            line: Some(12),
+            message: "character times of \"a\\b\" are not sorted".to_string(),
+        },
+    ];
+    assert_eq!(
+        lint::to_json(&diagnostics),
+        "[{\"line\":null,\"severity\":\"error\",\"message\":\"the chart has no notes\"},\
+         {\"line\":12,\"severity\":\"warning\",\"message\":\"character times of \\\"a\\\\b\\\" are not sorted\"}]"
+    );
+}
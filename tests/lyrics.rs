@@ -0,0 +1,86 @@
+// Synced lyrics become timed lines, and words in enhanced LRC. Lyric files
+// are user input: timestamps that parse as numbers but are not times are
+// refused instead of crashing the sort.
+use mechanical::lyrics::{self, Word, DEFAULT_LINE_LENGTH};
+
+fn words(pairs: &[(f64, &str)]) -> Vec<Word> {
+    pairs.iter().map(|(time, text)| Word { time: *time, text: text.to_string() }).collect()
+}
+
+#[test]
+fn lrc_lines_last_until_the_next_one() {
+    let contents = "[ti:Song]\n[ar:Band]\n[by:Someone]\n\
+                    [00:01.00]first line\n[00:02.50][00:04.00]  chorus  \n[00:03.00]\n";
+    let lyrics = lyrics::parse_lrc(contents).unwrap();
+    assert_eq!(lyrics.metadata.title, "Song");
+    assert_eq!(lyrics.metadata.artist, "Band");
+    assert_eq!(lyrics.metadata.creator, "Someone");
+    let lines: Vec<(f64, f64, &str)> =
+        lyrics.lines.iter().map(|line| (line.start, line.end, line.text.as_str())).collect();
+    // The empty line only ends the chorus before it
+    assert_eq!(
+        lines,
+        [(1.0, 2.5, "first line"), (2.5, 3.0, "chorus"), (4.0, 4.0 + DEFAULT_LINE_LENGTH, "chorus")]
+    );
+    assert!(lyrics.lines.iter().all(|line| line.words.is_empty()));
+}
+
+#[test]
+fn enhanced_lrc_times_every_word() {
+    let contents = "[00:01.00]<00:01.00>Hello <00:01.50>big <00:02.00>world\n[00:03.00]Hi <00:03.50>there\n";
+    let lyrics = lyrics::parse_lrc(contents).unwrap();
+    assert_eq!(lyrics.lines[0].text, "Hello big world");
+    assert_eq!(lyrics.lines[0].words, words(&[(1.0, "Hello"), (1.5, "big"), (2.0, "world")]));
+    // Text before the first timestamp is sung at the line time
+    assert_eq!(lyrics.lines[1].words, words(&[(3.0, "Hi"), (3.5, "there")]));
+}
+
+#[test]
+fn lrc_text_keeps_what_is_not_a_timestamp() {
+    let contents = "[00:01.00]I <3 you\n[00:02.00]<00:02.00>a <b> <00:02.50>c <00:NaN>d\n";
+    let lyrics = lyrics::parse_lrc(contents).unwrap();
+    assert_eq!(lyrics.lines[0].text, "I <3 you");
+    assert!(lyrics.lines[0].words.is_empty());
+    assert_eq!(lyrics.lines[1].text, "a <b> c <00:NaN>d");
+    assert_eq!(lyrics.lines[1].words, words(&[(2.0, "a <b>"), (2.5, "c <00:NaN>d")]));
+}
+
+#[test]
+fn lrc_offset_shows_the_lyrics_sooner() {
+    let contents = "[offset:+500]\n[00:01.00]<00:01.00>one <00:01.25>two\n[00:02.00]three\n";
+    let lyrics = lyrics::parse_lrc(contents).unwrap();
+    assert_eq!((lyrics.lines[0].start, lyrics.lines[0].end), (0.5, 1.5));
+    assert_eq!(lyrics.lines[0].words, words(&[(0.5, "one"), (0.75, "two")]));
+
+    let lyrics = lyrics::parse_lrc("[offset:-250]\n[00:01.00]one\n").unwrap();
+    assert_eq!(lyrics.lines[0].start, 1.25);
+}
+
+#[test]
+fn lrc_timestamps_must_be_finite() {
+    let contents = "[00:01.00]first\n[nan:00.00]not a time\n[00:inf]not either\n[00:02.00]second\n";
+    let lyrics = lyrics::parse_lrc(contents).unwrap();
+    let texts: Vec<&str> = lyrics.lines.iter().map(|line| line.text.as_str()).collect();
+    assert_eq!(texts, ["first", "second"]);
+    assert!(lyrics::parse_lrc("[offset:inf]\n[00:01.00]first\n").is_err());
+}
+
+#[test]
+fn srt_cues_become_lines() {
+    let contents = "\u{feff}2\n00:00:03,500 --> 00:00:05,000 X1:0\n<i>second</i>\n{\\an8}cue\n\n\
+                    00:00:01,000 --> 00:00:02,250\nfirst\n";
+    let lyrics = lyrics::parse_srt(contents).unwrap();
+    let lines: Vec<(f64, f64, &str)> =
+        lyrics.lines.iter().map(|line| (line.start, line.end, line.text.as_str())).collect();
+    assert_eq!(lines, [(1.0, 2.25, "first"), (3.5, 5.0, "second cue")]);
+}
+
+#[test]
+fn srt_timestamps_must_be_finite() {
+    let good = "1\n00:00:01,000 --> 00:00:02,000\nfirst\n";
+    assert_eq!(lyrics::parse_srt(good).unwrap().lines.len(), 1);
+    for timing in &["00:00:nan --> 00:00:02,000", "00:00:01,000 --> inf:00:02,000"] {
+        let contents = format!("1\n{}\nfirst\n\n2\n00:00:03,000 --> 00:00:04,000\nsecond\n", timing);
+        assert!(lyrics::parse_srt(&contents).is_err(), "{}", timing);
+    }
+}
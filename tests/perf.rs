@@ -0,0 +1,34 @@
+// Frame statistics keep the last frames only and average each stage apart.
+use mechanical::perf::{self, FrameStats};
+
+const EPSILON: f64 = 1e-9;
+
+#[test]
+fn frames_per_second_follow_the_last_frames() {
+    let mut stats = FrameStats::new();
+    assert_eq!(stats.fps(), None);
+    for _ in 0..perf::HISTORY {
+        stats.frame(0.050);
+    }
+    stats.frame(0.100);
+    for _ in 0..perf::HISTORY - 1 {
+        stats.frame(1.0 / 60.0);
+    }
+    assert_eq!(stats.frame_times().count(), perf::HISTORY);
+    assert!((stats.worst_frame().unwrap() - 0.100).abs() < EPSILON);
+    let total = 0.100 + (perf::HISTORY - 1) as f64 / 60.0;
+    assert!((stats.fps().unwrap() - perf::HISTORY as f64 / total).abs() < EPSILON);
+}
+
+#[test]
+fn stages_are_averaged_in_the_order_first_timed() {
+    let mut stats = FrameStats::new();
+    stats.stage("set_widgets", 0.002);
+    stats.stage("ui.draw", 0.001);
+    stats.stage("set_widgets", 0.004);
+    let stages = stats.stages();
+    assert_eq!(stages.len(), 2);
+    assert_eq!(stages[0].0, "set_widgets");
+    assert!((stages[0].1 - 0.003).abs() < EPSILON);
+    assert_eq!(stages[1].0, "ui.draw");
+}
@@ -0,0 +1,84 @@
+// Practice at a reduced rate keeps the windows, the offsets and the highway
+// the same in real time, and marks the play with its rate.
+use mechanical::chart::Chart;
+use mechanical::gameplay::Gameplay;
+use mechanical::highway::{self, Paint, Rect, JUDGEMENT_LINE_MARGIN};
+use mechanical::judgement::{Judgement, Windows};
+use mechanical::practice;
+use mechanical::profile::Profile;
+use mechanical::replay::Replay;
+use mechanical::results::Results;
+
+const EPSILON: f64 = 1e-9;
+
+#[test]
+fn rates_are_marked_as_mods() {
+    assert!(practice::valid_rate(0.75));
+    assert!(practice::valid_rate(1.0));
+    assert!(!practice::valid_rate(0.25));
+    assert!(!practice::valid_rate(0.95));
+    // The mod names the exact rate
+    assert!(!practice::valid_rate(0.755));
+
+    let mut gameplay = Gameplay::new(None);
+    assert_eq!(gameplay.rate(), 1.0);
+    gameplay.set_rate(1.0);
+    assert!(gameplay.mods.is_empty());
+    gameplay.mods.push("hidden".to_string());
+    gameplay.set_rate(0.75);
+    assert_eq!(gameplay.mods, ["hidden", "0.75x"]);
+    assert_eq!(gameplay.rate(), 0.75);
+}
+
+#[test]
+fn setting_the_rate_again_replaces_it() {
+    let mut gameplay = Gameplay::new(None);
+    gameplay.set_rate(0.5);
+    gameplay.set_rate(0.5);
+    assert_eq!(gameplay.mods, ["0.50x"]);
+    assert!((gameplay.windows.good - Windows::default().good * 0.5).abs() < EPSILON);
+    gameplay.set_rate(0.8);
+    assert_eq!(gameplay.mods, ["0.80x"]);
+    assert!((gameplay.windows.good - Windows::default().good * 0.8).abs() < EPSILON);
+    gameplay.set_rate(1.0);
+    assert!(gameplay.mods.is_empty());
+    assert_eq!(gameplay.windows, Windows::default());
+}
+
+#[test]
+fn windows_stay_as_wide_in_real_time() {
+    let chart = Chart::parse("[Notes]\n1.000 key a\n").unwrap();
+    let mut gameplay = Gameplay::new(Some(chart.clone()));
+    gameplay.set_rate(0.5);
+    assert!((gameplay.windows.perfect - Windows::default().perfect * 0.5).abs() < EPSILON);
+    // 30 ms late in media time is 60 ms late in real time
+    gameplay.key_down('a', 1.030);
+    gameplay.update(2.0);
+    assert_eq!(gameplay.score.count(Judgement::Great), 1);
+    let results = Results::new(&gameplay, 10, 4);
+    assert!((results.mean_offset.unwrap() - 0.060).abs() < 1e-6);
+
+    // Replays keep the rate and the windows it was played with
+    let replay = Replay::decode(&gameplay.replay().unwrap().encode()).unwrap();
+    assert_eq!(practice::rate(&replay.mods), 0.5);
+    assert_eq!(replay.verify(&chart).unwrap(), gameplay.score);
+}
+
+#[test]
+fn the_highway_scrolls_in_real_time() {
+    let area = Rect {
+        x: 0.0,
+        y: 0.0,
+        w: 800.0,
+        h: 600.0,
+    };
+    let chart = Chart::parse("[Notes]\n1.000 key a\n").unwrap();
+    let mut gameplay = Gameplay::new(Some(chart));
+    gameplay.set_rate(0.5);
+    let profile = Profile::new("player");
+    let frame = highway::frame(&gameplay, &profile, area, 0.75);
+    let note = frame.quads.iter().find(|quad| quad.paint == Paint::Note).unwrap();
+    // A quarter of a media second is half a real second away
+    let line_y = area.bottom() + JUDGEMENT_LINE_MARGIN;
+    assert!((note.rect.bottom() - (line_y + 0.5 * profile.scroll_speed)).abs() < EPSILON);
+}
@@ -0,0 +1,54 @@
+// Profiles keep their settings and the last one used through the profiles
+// file, and bound keys play the chart keys they are bound to.
+use mechanical::profile::{self, Profile, Profiles, Theme};
+
+const PROFILES: &str = "last=bo\n[ana]\nscroll_speed=450\ntheme=light\nbind=j a\nbind=k space\n[bo]\naudio_offset=0.025\n";
+
+#[test]
+fn profiles_survive_the_file() {
+    let profiles = Profiles::parse(PROFILES).unwrap();
+    assert_eq!(profiles.profiles.len(), 2);
+    assert_eq!(profiles.last_profile().unwrap().name, "bo");
+    let ana = profiles.get("ana").unwrap();
+    assert_eq!(ana.scroll_speed, 450.0);
+    assert_eq!(ana.theme, Theme::Light);
+    assert_eq!(ana.bindings, vec![('j', 'a'), ('k', ' ')]);
+    assert_eq!(profiles.get("bo").unwrap().audio_offset, 0.025);
+    assert_eq!(Profiles::parse(&profiles.to_text()).unwrap(), profiles);
+}
+
+#[test]
+fn bound_keys_play_their_chart_key() {
+    let mut profile = Profile::new("ana");
+    profile.set("bind", "j a").unwrap();
+    assert_eq!(profile.key('j'), 'a');
+    assert_eq!(profile.key('a'), 'a');
+    // Binding again replaces, binding a key to itself unbinds it
+    profile.bind('j', 's');
+    assert_eq!(profile.key('j'), 's');
+    profile.bind('j', 'j');
+    assert!(profile.bindings.is_empty());
+}
+
+#[test]
+fn selecting_a_profile_creates_and_remembers_it() {
+    let mut profiles = Profiles::parse(PROFILES).unwrap();
+    assert_eq!(profiles.select("ana").unwrap().scroll_speed, 450.0);
+    assert_eq!(profiles.last.as_deref(), Some("ana"));
+    assert_eq!(profiles.select("cy").unwrap(), &Profile::new("cy"));
+    assert_eq!(profiles.profiles.len(), 3);
+    assert!(profiles.select("").is_err());
+    assert!(profiles.select("[cy]").is_err());
+    assert!(!profile::valid_name(" cy"));
+}
+
+#[test]
+fn invalid_settings_are_errors() {
+    let mut profile = Profile::new("ana");
+    assert!(profile.set("scroll_speed", "-1").is_err());
+    assert!(profile.set("theme", "pink").is_err());
+    assert!(profile.set("bind", "j").is_err());
+    assert!(profile.set("volume", "1").is_err());
+    assert!(Profiles::parse("theme=dark\n").is_err());
+    assert!(Profiles::parse("[ana]\n[ana]\n").is_err());
+}
@@ -0,0 +1,106 @@
+// Replay files keep every input and the claimed score exactly, and play
+// back the same way as the live play they were recorded from.
+use std::cmp::Ordering;
+
+use mechanical::chart::Chart;
+use mechanical::gameplay::Gameplay;
+use mechanical::ghost::Ghost;
+use mechanical::replay::{KeyAction, Playback, Replay};
+
+const CHART: &str = "[Metadata]\ntitle=Test\naudio=test.ogg\n[Notes]\n1.000 key a\n2.000 hold b 3.000\n";
+
+fn played() -> Gameplay {
+    let mut gameplay = Gameplay::new(Some(Chart::parse(CHART).unwrap()));
+    gameplay.mods.push("test".to_string());
+    gameplay.key_down('a', 1.012_345_6);
+    gameplay.key_up('a', 1.1);
+    gameplay.key_down('é', 1.5);
+    gameplay.key_down('b', 1.98);
+    gameplay.key_up('b', 2.9);
+    gameplay.update(4.0);
+    gameplay
+}
+
+#[test]
+fn replay_survives_encoding() {
+    let replay = played().replay().unwrap();
+    assert_eq!(replay.inputs.len(), 5);
+    assert_eq!(replay.inputs[0].time, 1.012_346);
+    assert_eq!(replay.inputs[1].action, KeyAction::Up);
+    assert_eq!(replay.chart_hash, Chart::parse(CHART).unwrap().hash());
+
+    let bytes = replay.encode();
+    assert!(bytes.len() < 100, "{} bytes", bytes.len());
+    assert_eq!(Replay::decode(&bytes).unwrap(), replay);
+}
+
+#[test]
+fn truncated_replay_is_an_error() {
+    let bytes = played().replay().unwrap().encode();
+    assert!(Replay::decode(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Replay::decode(b"MRCH\x01").is_err());
+}
+
+#[test]
+fn replay_verifies_against_its_chart() {
+    let replay = played().replay().unwrap();
+    let chart = Chart::parse(CHART).unwrap();
+    assert_eq!(replay.verify(&chart).unwrap(), replay.score);
+
+    let mut tampered = replay.clone();
+    tampered.score.points += 100;
+    assert!(tampered.verify(&chart).is_err());
+
+    let other = Chart::parse(&CHART.replace("1.000 key a", "1.000 key c")).unwrap();
+    assert!(replay.verify(&other).is_err());
+}
+
+#[test]
+fn notes_left_at_the_end_of_the_media_verify() {
+    // The media ends on the last note, before its windows have passed
+    let chart = Chart::parse("[Notes]\n1.000 key a\n2.000 hold b 3.000\n3.000 key c\n").unwrap();
+    let mut gameplay = Gameplay::new(Some(chart.clone()));
+    gameplay.key_down('a', 1.0);
+    gameplay.key_down('b', 2.0);
+    gameplay.update(3.0);
+    gameplay.finish();
+    assert!((0..chart.notes.len()).all(|index| !gameplay.is_pending(index)));
+    let replay = Replay::decode(&gameplay.replay().unwrap().encode()).unwrap();
+    assert_eq!(replay.verify(&chart).unwrap(), gameplay.score);
+}
+
+#[test]
+fn playback_gives_the_same_judgements_at_any_frame_rate() {
+    let gameplay = played();
+    let replay = gameplay.replay().unwrap();
+    let chart = Chart::parse(CHART).unwrap();
+    let mut playback = Playback::new(replay);
+    let mut replayed = playback.start(chart);
+    // Uneven frames, and a seek back to the start halfway
+    for &time in &[0.3, 1.05, 1.6, 0.2, 0.9, 1.9, 2.1, 2.95, 4.0] {
+        playback.seek(&mut replayed, time);
+    }
+    assert_eq!(replayed.judgements(), gameplay.judgements());
+    assert_eq!(replayed.score, gameplay.score);
+}
+
+#[test]
+fn ghost_races_in_lock_step() {
+    let chart = Chart::parse(CHART).unwrap();
+    let mut ghost = Ghost::new(played().replay().unwrap(), &chart).unwrap();
+    let mut live = Gameplay::new(Some(chart));
+
+    // Later than the ghost on the first note
+    live.key_down('a', 1.1);
+    ghost.update(1.1);
+    assert_eq!(ghost.compare_note(&live, 0), Some(Ordering::Less));
+    assert!(ghost.lead(&live) < 0);
+    // Not judged yet by either
+    assert_eq!(ghost.compare_note(&live, 1), None);
+
+    live.key_down('b', 2.0);
+    live.update(4.0);
+    ghost.update(4.0);
+    assert_eq!(ghost.compare_note(&live, 1), Some(Ordering::Greater));
+    assert_eq!(ghost.gameplay.score, played().score);
+}
@@ -0,0 +1,116 @@
+// When the event loop wakes up: waiting for events outside of animations,
+// fixed rate ticks and paced frames while animating, and switching between
+// the two from what setting the UI answers.
+use std::time::{Duration, Instant};
+
+use mechanical::schedule::{self, LoopConfig, Schedule, WakeUp};
+
+fn millis(milliseconds: u64) -> Duration {
+    Duration::from_millis(milliseconds)
+}
+
+fn animating(config: LoopConfig, now: Instant) -> Schedule {
+    let mut schedule = Schedule::new(config, now);
+    assert!(schedule.ui_set(now, false, true));
+    schedule
+}
+
+#[test]
+fn the_loop_waits_for_events_until_something_is_drawn() {
+    let now = Instant::now();
+    let mut schedule = Schedule::new(LoopConfig::default(), now);
+    assert!(schedule.is_waiting());
+    assert_eq!(schedule.wake_up(), WakeUp::Wait);
+
+    // Redrawing sets the UI again after a while, in case more changed
+    assert!(schedule.ui_set(now, true, false));
+    assert!(!schedule.is_waiting());
+    assert_eq!(schedule.wake_up(), WakeUp::At(now + schedule::UI_INTERVAL));
+
+    let later = now + schedule::UI_INTERVAL;
+    assert!(!schedule.ui_set(later, false, false));
+    assert!(schedule.is_waiting());
+    assert_eq!(schedule.wake_up(), WakeUp::Wait);
+}
+
+#[test]
+fn animating_ticks_at_a_fixed_rate() {
+    let now = Instant::now();
+    let mut schedule = animating(LoopConfig { tick_rate: 100.0, max_fps: Some(60.0) }, now);
+    assert!(schedule.is_animating());
+    assert_eq!(schedule.tick_step(), millis(10));
+    assert_eq!(schedule.ticks(now), 0);
+    assert_eq!(schedule.ticks(now + millis(25)), 2);
+    // The 5ms left count towards the next tick
+    assert_eq!(schedule.ticks(now + millis(30)), 1);
+    assert_eq!(schedule.ticks(now + millis(39)), 0);
+    assert_eq!(schedule.ticks(now + millis(40)), 1);
+}
+
+#[test]
+fn a_stall_is_not_caught_up() {
+    let now = Instant::now();
+    let mut schedule = animating(LoopConfig { tick_rate: 100.0, max_fps: Some(60.0) }, now);
+    assert_eq!(schedule.ticks(now + Duration::from_secs(1)), schedule::MAX_TICKS_PER_FRAME);
+    assert_eq!(schedule.ticks(now + Duration::from_secs(1) + millis(5)), 0);
+}
+
+#[test]
+fn frames_keep_to_their_step() {
+    let now = Instant::now();
+    let mut schedule = animating(LoopConfig { tick_rate: 1000.0, max_fps: Some(50.0) }, now);
+    // The first frame is right away
+    assert!(schedule.frame_due(now));
+    assert!(!schedule.frame_due(now + millis(19)));
+    // A frame handled late does not push the next ones back
+    assert!(schedule.frame_due(now + millis(25)));
+    assert!(!schedule.frame_due(now + millis(39)));
+    assert!(schedule.frame_due(now + millis(40)));
+    // Frames that fell behind draw the next one right away, then keep to
+    // their step from there
+    assert!(schedule.frame_due(now + millis(200)));
+    assert!(schedule.frame_due(now + millis(205)));
+    assert!(!schedule.frame_due(now + millis(219)));
+    assert!(schedule.frame_due(now + millis(220)));
+}
+
+#[test]
+fn animating_wakes_up_for_the_next_tick_or_frame() {
+    let now = Instant::now();
+    let mut schedule = animating(LoopConfig { tick_rate: 100.0, max_fps: Some(50.0) }, now);
+    assert_eq!(schedule.wake_up(), WakeUp::At(now));
+    assert!(schedule.frame_due(now));
+    schedule.ticks(now);
+    assert_eq!(schedule.wake_up(), WakeUp::At(now + millis(10)));
+    schedule.ticks(now + millis(15));
+    assert_eq!(schedule.wake_up(), WakeUp::At(now + millis(20)));
+    // The next frame comes before the next tick
+    schedule.ticks(now + millis(18));
+    assert_eq!(schedule.wake_up(), WakeUp::At(now + millis(20)));
+
+    // Without a frame limit, as fast as possible
+    let schedule = animating(LoopConfig { tick_rate: 100.0, max_fps: None }, now);
+    assert_eq!(schedule.wake_up(), WakeUp::Poll);
+}
+
+#[test]
+fn setting_the_ui_switches_animations_on_and_off() {
+    let now = Instant::now();
+    let mut schedule = Schedule::new(LoopConfig::default(), now);
+    // Ticks are counted from when the animation starts
+    let start = now + Duration::from_secs(5);
+    assert!(schedule.ui_set(start, false, true));
+    assert_eq!(schedule.ticks(start), 0);
+
+    // Every frame of an animation is drawn
+    assert!(schedule.ui_set(start + millis(20), false, true));
+    assert!(schedule.is_animating());
+
+    // The UI is set once more when it stops, then the loop waits
+    let end = start + millis(40);
+    assert!(schedule.ui_set(end, false, false));
+    assert!(!schedule.is_animating());
+    assert_eq!(schedule.wake_up(), WakeUp::At(end));
+    assert!(!schedule.ui_set(end, false, false));
+    assert_eq!(schedule.wake_up(), WakeUp::Wait);
+}
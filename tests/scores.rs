@@ -0,0 +1,119 @@
+// The high score database keeps every play across reopening, and reads
+// what it can of a damaged file.
+use std::fs;
+use std::path::PathBuf;
+
+use mechanical::chart::Chart;
+use mechanical::judgement::Judgement;
+use mechanical::score::Score;
+use mechanical::scores::{Entry, ScoreDatabase};
+
+const CHART: &str = "[Metadata]\ntitle=Test\ndifficulty=Hard\naudio=test.ogg\n[Notes]\n1.000 key a\n2.000 key b\n";
+
+// A database file of its own for each test, as they run in parallel
+fn database_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("mechanical-scores-{}-{}", name, std::process::id()));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn entry(profile: &str, judgements: &[Judgement], date: u64) -> Entry {
+    let mut score = Score::new();
+    for judgement in judgements {
+        score.add_key(*judgement);
+    }
+    Entry::new(&Chart::parse(CHART).unwrap(), profile, score, Vec::new(), date, None)
+}
+
+#[test]
+fn plays_survive_reopening() {
+    let path = database_path("reopen");
+    let mut database = ScoreDatabase::open(&path).unwrap();
+    assert!(database.entries.is_empty());
+    let mut first = entry("ana", &[Judgement::Perfect, Judgement::Great], 10);
+    first.title = "Tabs\tand\\nothing,".to_string();
+    first.mods = vec!["half, speed".to_string(), "hidden\\".to_string()];
+    first.replay = Some(PathBuf::from("replays/Test 10.mrreplay"));
+    database.add(first.clone()).unwrap();
+    database.add(entry("bo", &[Judgement::Miss, Judgement::Good], 20)).unwrap();
+
+    let reopened = ScoreDatabase::open(&path).unwrap();
+    assert_eq!(reopened.skipped, 0);
+    assert_eq!(reopened.entries.len(), 2);
+    assert_eq!(reopened.entries[0].mods, first.mods);
+    assert_eq!(reopened.entries[0].title, first.title);
+    assert_eq!(reopened.entries[0].replay, first.replay);
+    assert_eq!(reopened.entries[0].score.counts, first.score.counts);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn leaderboard_is_sorted_per_chart_and_profile() {
+    let path = database_path("leaderboard");
+    let mut database = ScoreDatabase::open(&path).unwrap();
+    database.add(entry("ana", &[Judgement::Good, Judgement::Good], 1)).unwrap();
+    database.add(entry("bo", &[Judgement::Perfect, Judgement::Perfect], 2)).unwrap();
+    database.add(entry("ana", &[Judgement::Perfect, Judgement::Great], 3)).unwrap();
+    database.add(entry("bo", &[Judgement::Perfect, Judgement::Perfect], 4)).unwrap();
+    let mut other = entry("ana", &[Judgement::Perfect, Judgement::Perfect], 5);
+    other.chart_hash += 1;
+    database.add(other).unwrap();
+
+    let hash = Chart::parse(CHART).unwrap().hash();
+    let dates: Vec<u64> = database.top(hash, None, 3).iter().map(|entry| entry.date).collect();
+    // Ties go to the earliest play
+    assert_eq!(dates, vec![2, 4, 3]);
+    assert_eq!(database.personal_best(hash, "ana").unwrap().date, 3);
+    assert!(database.personal_best(hash, "cy").is_none());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn damaged_lines_are_skipped() {
+    let path = database_path("damaged");
+    let mut database = ScoreDatabase::open(&path).unwrap();
+    database.add(entry("ana", &[Judgement::Perfect], 1)).unwrap();
+    let mut contents = fs::read_to_string(&path).unwrap();
+    contents += "not a play\n";
+    contents += "0123\tcut short\n";
+    fs::write(&path, contents).unwrap();
+    database = ScoreDatabase::open(&path).unwrap();
+    assert_eq!(database.entries.len(), 1);
+    assert_eq!(database.skipped, 2);
+
+    // Plays are still added after the damaged lines
+    database.add(entry("ana", &[Judgement::Great], 2)).unwrap();
+    assert_eq!(ScoreDatabase::open(&path).unwrap().entries.len(), 2);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn plays_after_a_truncated_line_start_on_a_line_of_their_own() {
+    let path = database_path("truncated");
+    let mut database = ScoreDatabase::open(&path).unwrap();
+    database.add(entry("ana", &[Judgement::Perfect], 1)).unwrap();
+    database.add(entry("ana", &[Judgement::Great], 2)).unwrap();
+    // A crash in the middle of the last play
+    let contents = fs::read_to_string(&path).unwrap();
+    fs::write(&path, &contents[..contents.len() - 10]).unwrap();
+    database = ScoreDatabase::open(&path).unwrap();
+    assert_eq!(database.entries.len(), 1);
+    assert_eq!(database.skipped, 1);
+
+    database.add(entry("bo", &[Judgement::Good], 3)).unwrap();
+    let reopened = ScoreDatabase::open(&path).unwrap();
+    assert_eq!(reopened.skipped, 1);
+    assert_eq!(reopened.entries.len(), 2);
+    assert_eq!(reopened.entries[1].profile, "bo");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn newer_schema_is_refused() {
+    let path = database_path("newer");
+    fs::write(&path, "mechanical scores 99\n").unwrap();
+    assert!(ScoreDatabase::open(&path).is_err());
+    fs::write(&path, "something else\n").unwrap();
+    assert!(ScoreDatabase::open(&path).is_err());
+    fs::remove_file(&path).unwrap();
+}
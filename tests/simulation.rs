@@ -0,0 +1,263 @@
+// Plays charts with a scripted input timeline on a manual clock, the way the
+// player would frame by frame, and checks the judgements, score and combo.
+use mechanical::chart::Chart;
+use mechanical::clock::{GameClock, ManualClock, OffsetClock};
+use mechanical::gameplay::Gameplay;
+use mechanical::judgement::{Judgement, Windows};
+use mechanical::results::{self, Results};
+
+use Action::{Down, Up};
+use Judgement::{Good, Great, Miss, Perfect};
+
+// Windows and frame length that are exact in binary, so the boundary cases
+// don't depend on rounding
+const WINDOWS: Windows = Windows {
+    perfect: 0.031_25,
+    great: 0.062_5,
+    good: 0.125,
+};
+const FRAME: f64 = 1.0 / 64.0;
+// Smallest step past a window in these tests
+const EPSILON: f64 = 1.0 / 1024.0;
+
+#[derive(Copy, Clone, Debug)]
+enum Action {
+    Down,
+    Up,
+}
+
+fn chart(notes: &[&str]) -> Chart {
+    let contents = format!(
+        "[Metadata]\ntitle=Test\naudio=test.ogg\n[Timing]\n0.000 120.000 4\n[Notes]\n{}\n",
+        notes.join("\n")
+    );
+    Chart::parse(&contents).unwrap()
+}
+
+// Run the frames up to `time`, then the frame at `time`
+fn run_until(gameplay: &mut Gameplay, clock: &mut ManualClock, time: f64) {
+    while clock.time() + FRAME < time {
+        clock.advance(FRAME);
+        gameplay.update(clock.time());
+    }
+    clock.set(time);
+    gameplay.update(clock.time());
+}
+
+// Play `notes` with the inputs, sorted by time, until a second after the
+// last note ends
+fn simulate(notes: &[&str], inputs: &[(f64, char, Action)]) -> Gameplay {
+    let chart = chart(notes);
+    let end = chart.notes.iter().map(|note| note.end_time()).fold(0.0, f64::max) + 1.0;
+    let mut gameplay = Gameplay::new(Some(chart));
+    gameplay.windows = WINDOWS;
+    let mut clock = ManualClock::default();
+    for &(time, key, action) in inputs {
+        run_until(&mut gameplay, &mut clock, time);
+        match action {
+            Down => gameplay.key_down(key, clock.time()),
+            Up => gameplay.key_up(key, clock.time()),
+        }
+    }
+    run_until(&mut gameplay, &mut clock, end);
+    gameplay
+}
+
+// Press and release right away
+fn taps(taps: &[(f64, char)]) -> Vec<(f64, char, Action)> {
+    taps.iter()
+        .flat_map(|&(time, key)| vec![(time, key, Down), (time, key, Up)])
+        .collect()
+}
+
+fn judgements(gameplay: &Gameplay) -> Vec<Judgement> {
+    gameplay.judgements().iter().map(|judged| judged.judgement).collect()
+}
+
+#[test]
+fn keys_pressed_on_time_are_perfect() {
+    let gameplay = simulate(&["1.000 key a", "2.000 key s"], &taps(&[(1.0, 'a'), (2.0, 's')]));
+    assert_eq!(judgements(&gameplay), vec![Perfect, Perfect]);
+    assert_eq!(gameplay.score.points, 200);
+    assert_eq!(gameplay.score.combo, 2);
+    assert_eq!(gameplay.score.max_combo, 2);
+    assert_eq!(gameplay.score.accuracy(), 1.0);
+}
+
+#[test]
+fn window_boundaries_belong_to_the_better_judgement() {
+    let cases = [
+        (WINDOWS.perfect, Perfect),
+        (-WINDOWS.perfect, Perfect),
+        (WINDOWS.perfect + EPSILON, Great),
+        (WINDOWS.great, Great),
+        (-WINDOWS.great - EPSILON, Good),
+        (WINDOWS.good, Good),
+        (-WINDOWS.good, Good),
+    ];
+    for &(offset, expected) in &cases {
+        let gameplay = simulate(&["1.000 key a"], &taps(&[(1.0 + offset, 'a')]));
+        assert_eq!(judgements(&gameplay), vec![expected], "offset {}", offset);
+    }
+}
+
+#[test]
+fn late_press_after_the_good_window_is_a_miss() {
+    let gameplay = simulate(&["1.000 key a"], &taps(&[(1.0 + WINDOWS.good + EPSILON, 'a')]));
+    assert_eq!(judgements(&gameplay), vec![Miss]);
+    assert_eq!(gameplay.judgements()[0].offset, None);
+    assert_eq!(gameplay.score.points, 0);
+    assert_eq!(gameplay.score.combo, 0);
+}
+
+#[test]
+fn early_press_before_the_good_window_is_ignored() {
+    let inputs = taps(&[(1.0 - WINDOWS.good - EPSILON, 'a'), (1.0, 'a')]);
+    let gameplay = simulate(&["1.000 key a"], &inputs);
+    assert_eq!(judgements(&gameplay), vec![Perfect]);
+}
+
+#[test]
+fn wrong_key_does_not_hit() {
+    let gameplay = simulate(&["1.000 key a"], &taps(&[(1.0, 's')]));
+    assert_eq!(judgements(&gameplay), vec![Miss]);
+}
+
+#[test]
+fn press_hits_the_earliest_pending_note_of_its_key() {
+    let notes = ["1.000 key a", "1.0625 key a"];
+    let gameplay = simulate(&notes, &taps(&[(1.0625, 'a'), (1.0625, 'a')]));
+    assert_eq!(judgements(&gameplay), vec![Great, Perfect]);
+    assert_eq!(gameplay.judgements()[0].note, 0);
+    assert_eq!(gameplay.judgements()[0].offset, Some(0.0625));
+    assert_eq!(gameplay.judgements()[1].note, 1);
+}
+
+#[test]
+fn chord_hits_every_key() {
+    let notes = ["1.000 key a", "1.000 key s", "1.000 key d"];
+    let gameplay = simulate(&notes, &taps(&[(1.0, 'd'), (1.0, 'a'), (1.0, 's')]));
+    assert_eq!(judgements(&gameplay), vec![Perfect, Perfect, Perfect]);
+    assert_eq!(gameplay.score.max_combo, 3);
+}
+
+#[test]
+fn miss_breaks_the_combo() {
+    let notes = ["1.000 key a", "2.000 key s", "3.000 key d", "4.000 key f", "5.000 key g"];
+    let inputs = taps(&[(1.0, 'a'), (2.0, 's'), (4.0, 'f'), (5.0, 'g')]);
+    let gameplay = simulate(&notes, &inputs);
+    assert_eq!(judgements(&gameplay), vec![Perfect, Perfect, Miss, Perfect, Perfect]);
+    assert_eq!(gameplay.score.combo, 2);
+    assert_eq!(gameplay.score.max_combo, 2);
+    assert_eq!(gameplay.score.count(Miss), 1);
+    assert_eq!(gameplay.score.accuracy(), 0.8);
+}
+
+#[test]
+fn hold_held_to_the_end_is_judged_twice() {
+    let gameplay = simulate(&["1.000 hold a 2.000"], &[(1.0, 'a', Down), (2.5, 'a', Up)]);
+    assert_eq!(judgements(&gameplay), vec![Perfect, Perfect]);
+    assert_eq!(gameplay.score.points, 200);
+}
+
+#[test]
+fn hold_released_at_the_window_boundary_before_its_end() {
+    let inputs = [(1.0, 'a', Down), (2.0 - WINDOWS.good, 'a', Up)];
+    let gameplay = simulate(&["1.000 hold a 2.000"], &inputs);
+    assert_eq!(judgements(&gameplay), vec![Perfect, Good]);
+
+    let inputs = [(1.0, 'a', Down), (2.0 - WINDOWS.good - EPSILON, 'a', Up)];
+    let gameplay = simulate(&["1.000 hold a 2.000"], &inputs);
+    assert_eq!(judgements(&gameplay), vec![Perfect, Miss]);
+    assert_eq!(gameplay.score.combo, 0);
+    assert_eq!(gameplay.score.max_combo, 1);
+}
+
+#[test]
+fn missed_hold_head_misses_the_whole_hold() {
+    let gameplay = simulate(&["1.000 hold a 2.000"], &[(1.5, 'a', Down), (2.0, 'a', Up)]);
+    assert_eq!(judgements(&gameplay), vec![Miss, Miss]);
+}
+
+#[test]
+fn phrase_is_typed_with_the_keys_not_used_by_notes() {
+    let notes = ["1.000 typed 1.000,1.250,1.500 cat", "1.250 key x"];
+    let inputs = taps(&[(1.0, 'c'), (1.25, 'x'), (1.25 + WINDOWS.great, 'a'), (1.5, 't')]);
+    let gameplay = simulate(&notes, &inputs);
+    // The key note is judged when pressed, the phrase once fully typed
+    assert_eq!(judgements(&gameplay), vec![Perfect, Perfect, Great, Perfect]);
+    assert_eq!(gameplay.score.max_combo, 4);
+}
+
+#[test]
+fn phrase_with_a_mistake_erased_loses_typing_accuracy() {
+    let inputs = taps(&[(1.0, 'c'), (1.1, 'o'), (1.2, '\u{8}'), (1.25, 'a'), (1.5, 't')]);
+    let gameplay = simulate(&["1.000 typed 1.000,1.250,1.500 cat"], &inputs);
+    assert_eq!(judgements(&gameplay), vec![Perfect, Perfect, Perfect]);
+    // Three right out of four typed
+    assert_eq!(gameplay.score.points, 225);
+}
+
+#[test]
+fn same_inputs_give_the_same_play() {
+    let notes = ["0.500 key a", "1.000 hold s 1.500", "1.750 key d", "2.000 phrase 3.000 go"];
+    let inputs = [
+        (0.52, 'a', Down),
+        (0.55, 'a', Up),
+        (0.98, 's', Down),
+        (1.40, 's', Up),
+        (2.10, 'g', Down),
+        (2.20, 'o', Down),
+    ];
+    let first = simulate(&notes, &inputs);
+    let second = simulate(&notes, &inputs);
+    assert_eq!(first.judgements(), second.judgements());
+    assert_eq!(first.score, second.score);
+    assert_eq!(judgements(&first), vec![Perfect, Perfect, Good, Miss, Perfect, Perfect]);
+}
+
+#[test]
+fn results_spread_the_hits_around_their_targets() {
+    let notes = ["0.500 key a", "1.500 key a", "2.500 key a", "3.500 key a"];
+    let inputs = taps(&[(0.5 - 0.0625, 'a'), (1.5 + 0.0625, 'a'), (2.5 + 0.125, 'a')]);
+    let results = Results::new(&simulate(&notes, &inputs), 4, 2);
+    assert_eq!(results.histogram, vec![0, 1, 0, 2]);
+    assert_eq!(results.mean_offset, Some(0.125 / 3.0));
+    assert!((results.unstable_rate.unwrap() - 779.5).abs() < 0.1);
+    // Great, great in the first half, good and a miss in the second
+    assert_eq!(results.sections, vec![Some(0.7), Some(0.2)]);
+    assert_eq!(results.grade, "D");
+}
+
+#[test]
+fn grades_follow_the_accuracy() {
+    assert_eq!(results::grade(1.0), "SS");
+    assert_eq!(results::grade(0.95), "S");
+    assert_eq!(results::grade(0.949), "A");
+    assert_eq!(results::grade(0.0), "D");
+
+    let results = Results::new(&simulate(&["1.000 key a"], &[]), 4, 2);
+    assert_eq!(results.mean_offset, None);
+    assert_eq!(results.unstable_rate, None);
+}
+
+#[test]
+fn audio_offset_moves_keys_and_notes_together() {
+    // The sound comes out 1/8 s late: a press 3/64 s late for the player is
+    // past the good window on the media clock, but still a great
+    let offset = 0.125;
+    let press = 1.0 + offset + 0.046_875;
+    let mut gameplay = Gameplay::new(Some(chart(&["1.000 key a"])));
+    gameplay.windows = WINDOWS;
+    let mut clock = ManualClock::default();
+    while clock.time() + FRAME < press {
+        clock.advance(FRAME);
+        gameplay.update(OffsetClock::new(&clock, offset).time());
+    }
+    clock.set(press);
+    let heard = OffsetClock::new(&clock, offset);
+    gameplay.update(heard.time());
+    gameplay.key_down('a', heard.time());
+    gameplay.key_up('a', heard.time());
+    assert_eq!(judgements(&gameplay), [Great]);
+}
@@ -0,0 +1,101 @@
+// Phrases are judged on what was typed and when: wrong characters left in
+// are misses, the ones erased cost typing accuracy, and the first character
+// can come as early as a key.
+use mechanical::chart::{Chart, NoteKind, Phrase};
+use mechanical::gameplay::Gameplay;
+use mechanical::judgement::Judgement::{self, *};
+use mechanical::judgement::Windows;
+use mechanical::typing::{Keystroke, PhraseInput};
+
+const EPSILON: f64 = 1e-9;
+
+fn phrase_of(line: &str) -> (Phrase, f64) {
+    let chart = Chart::parse(&format!("[Notes]\n{}\n", line)).unwrap();
+    match chart.notes[0].kind {
+        NoteKind::Phrase(ref phrase) => (phrase.clone(), chart.notes[0].time),
+        ref kind => panic!("{:?} is not a phrase", kind),
+    }
+}
+
+fn typed(phrase: &Phrase, characters: &[(f64, char)]) -> PhraseInput {
+    let mut input = PhraseInput::new(phrase);
+    for (time, character) in characters {
+        match character {
+            '\u{8}' => input.backspace(),
+            character => {
+                input.type_char(*character, *time);
+            }
+        }
+    }
+    input
+}
+
+#[test]
+fn keystrokes_follow_the_cursor() {
+    let (phrase, _) = phrase_of("1.000 phrase 2.000 go");
+    let mut input = PhraseInput::new(&phrase);
+    assert_eq!(input.type_char('g', 1.0), Keystroke::Correct);
+    assert_eq!(input.type_char('x', 1.1), Keystroke::Wrong);
+    assert!(!input.is_correct_so_far());
+    input.backspace();
+    assert!(input.is_correct_so_far());
+    assert_eq!(input.cursor(), 1);
+    assert_eq!(input.type_char('o', 1.2), Keystroke::Correct);
+    assert!(input.is_complete());
+    assert_eq!(input.type_char('!', 1.3), Keystroke::Ignored);
+}
+
+#[test]
+fn wrong_characters_left_in_count_once() {
+    let (phrase, start) = phrase_of("1.000 phrase 2.000 cat");
+    let input = typed(&phrase, &[(1.0, 'c'), (1.1, 'o'), (1.2, 't')]);
+    let result = input.result(&phrase, start, &Windows::default());
+    assert_eq!(result.judgements, [Perfect, Miss, Perfect]);
+    assert_eq!((result.correct, result.mistakes, result.erased), (2, 1, 0));
+    // Two right out of three typed, the miss is not counted again
+    assert!((result.typing_accuracy() - 2.0 / 3.0).abs() < EPSILON);
+}
+
+#[test]
+fn erased_mistakes_are_typed_twice() {
+    let (phrase, start) = phrase_of("1.000 phrase 2.000 cat");
+    let input = typed(&phrase, &[(1.0, 'c'), (1.1, 'o'), (1.2, '\u{8}'), (1.3, 'a'), (1.4, 't')]);
+    let result = input.result(&phrase, start, &Windows::default());
+    assert_eq!(result.judgements, [Perfect, Perfect, Perfect]);
+    assert_eq!((result.correct, result.mistakes, result.erased), (3, 1, 1));
+    assert!((result.typing_accuracy() - 0.75).abs() < EPSILON);
+    assert_eq!(result.timing_accuracy(), 1.0);
+    assert_eq!(result.score(), 225);
+}
+
+#[test]
+fn characters_are_judged_on_their_timing() {
+    let windows = Windows::default();
+    // Each character on its own time
+    let (phrase, start) = phrase_of("1.000 typed 1.000,1.250 ab");
+    let input = typed(&phrase, &[(1.0625, 'a'), (1.25 - 0.125, 'b')]);
+    assert_eq!(input.result(&phrase, start, &windows).judgements, [Great, Good]);
+
+    // Anywhere in the window is perfect, outside it like a key
+    let (phrase, start) = phrase_of("1.000 phrase 2.000 abc");
+    let input = typed(&phrase, &[(1.0 - 0.0625, 'a'), (1.5, 'b'), (2.0 + 0.25, 'c')]);
+    let judgements: Vec<Judgement> = input.result(&phrase, start, &windows).judgements;
+    assert_eq!(judgements, [Great, Perfect, Miss]);
+}
+
+#[test]
+fn phrases_open_with_their_early_window() {
+    let chart = Chart::parse("[Notes]\n1.000 phrase 2.000 go\n").unwrap();
+    let mut gameplay = Gameplay::new(Some(chart));
+    gameplay.update(0.9);
+    gameplay.key_down('g', 1.0 - 0.0625);
+    gameplay.key_down('o', 1.5);
+    let judgements: Vec<Judgement> = gameplay.judgements().iter().map(|judged| judged.judgement).collect();
+    assert_eq!(judgements, [Great, Perfect]);
+
+    // Not before
+    let chart = Chart::parse("[Notes]\n1.000 phrase 2.000 go\n").unwrap();
+    let mut gameplay = Gameplay::new(Some(chart));
+    gameplay.key_down('g', 1.0 - 0.25);
+    assert!(gameplay.current_phrase().is_none());
+}